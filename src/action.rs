@@ -16,10 +16,22 @@ pub enum Action {
 
     /// `line` was replaced by a `new` one. As with `Deleted`, the position is
     /// given.
+    ///
+    /// `warnings` lists the links of the line whose conversion is less
+    /// reliable and should be checked by hand.
     Replaced {
         line: String,
         new: String,
         pos: usize,
+        warnings: Vec<String>,
+    },
+
+    /// `line` was left unchanged but some of its links need the attention of
+    /// the user, as explained by `warnings`.
+    Flagged {
+        line: String,
+        pos: usize,
+        warnings: Vec<String>,
     },
 }
 
@@ -29,27 +41,46 @@ impl Action {
         matches!(self, Action::Unchanged { line: _ })
     }
 
+    /// Position of the line, when the action is not `Action::Unchanged`.
+    pub fn pos(&self) -> Option<usize> {
+        match self {
+            Action::Unchanged { .. } => None,
+            Action::Deleted { pos, .. }
+            | Action::Replaced { pos, .. }
+            | Action::Flagged { pos, .. } => Some(*pos),
+        }
+    }
+
+    /// Leaves the line as it was, dropping any change or warning.
+    pub fn into_unchanged(self) -> Self {
+        match self {
+            Action::Unchanged { .. } => self,
+            Action::Flagged { line, .. } => Action::Unchanged { line },
+            Action::Deleted { mut line, .. } | Action::Replaced { mut line, .. } => {
+                line.push('\n');
+                Action::Unchanged { line }
+            }
+        }
+    }
+
     /// Returns the new line to add.
     ///
     /// - `Action::Unchanged` returns its line unchanged.
     /// - `Action::Deleted` returns an empty line (without ending `\n`).
     /// - `Action::Replaced` returns its `new` line.
+    /// - `Action::Flagged` returns its line unchanged.
     pub fn as_new_line(&self) -> &str {
         match self {
-            Action::Unchanged { line } => line,
+            Action::Unchanged { line } | Action::Flagged { line, .. } => line,
             Action::Deleted { line: _, pos: _ } => "",
-            Action::Replaced {
-                line: _,
-                new,
-                pos: _,
-            } => new,
+            Action::Replaced { new, .. } => new,
         }
     }
 }
 
 impl fmt::Display for Action {
-    /// Special display that will only write `Deleted` and `Replaced` variants,
-    /// unchanged lines are simply ignored.
+    /// Special display that will only write `Deleted`, `Replaced` and
+    /// `Flagged` variants, unchanged lines are simply ignored.
     ///
     /// Note that colors are used and the output it not stable: it is intended
     /// to be read by humans, not machines, use the variants and the
@@ -64,13 +95,35 @@ impl fmt::Display for Action {
                 Color::Red.paint(line.trim_end_matches('\n')),
                 Color::Yellow.paint("Deleted local link (of the form '[name]: name')")
             ),
-            Action::Replaced { line, new, pos } => write!(
-                f,
-                "{:5}:  \"{}\"\n        \"{}\"",
+            Action::Replaced {
+                line,
+                new,
                 pos,
-                Color::Red.paint(line.trim_end_matches('\n')),
-                Color::Green.paint(new.trim_end_matches('\n'))
-            ),
+                warnings,
+            } => {
+                write!(
+                    f,
+                    "{:5}:  \"{}\"\n        \"{}\"",
+                    pos,
+                    Color::Red.paint(line.trim_end_matches('\n')),
+                    Color::Green.paint(new.trim_end_matches('\n'))
+                )?;
+                for w in warnings {
+                    write!(f, "\n        {}", Color::Yellow.paint(w))?;
+                }
+                Ok(())
+            }
+            Action::Flagged {
+                line,
+                pos,
+                warnings,
+            } => {
+                write!(f, "{:5}:  \"{}\"", pos, line.trim_end_matches('\n'))?;
+                for w in warnings {
+                    write!(f, "\n        {}", Color::Yellow.paint(w))?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -95,7 +148,15 @@ mod tests {
         assert!(!Action::Replaced {
             line: "line".into(),
             new: "new".into(),
-            pos: 3
+            pos: 3,
+            warnings: Vec::new(),
+        }
+        .is_unchanged());
+
+        assert!(!Action::Flagged {
+            line: "line".into(),
+            pos: 3,
+            warnings: vec!["warning".into()],
         }
         .is_unchanged());
     }
@@ -123,10 +184,57 @@ mod tests {
             Action::Replaced {
                 line: "line".into(),
                 new: "new".into(),
-                pos: 3
+                pos: 3,
+                warnings: Vec::new(),
             }
             .as_new_line(),
             "new"
         );
+
+        assert_eq!(
+            Action::Flagged {
+                line: "line".into(),
+                pos: 3,
+                warnings: vec!["warning".into()],
+            }
+            .as_new_line(),
+            "line"
+        );
+    }
+
+    #[test]
+    fn into_unchanged() {
+        let unchanged = Action::Unchanged {
+            line: "line\n".into(),
+        };
+
+        assert_eq!(unchanged.clone().into_unchanged(), unchanged);
+        assert_eq!(
+            Action::Deleted {
+                line: "line".into(),
+                pos: 3
+            }
+            .into_unchanged(),
+            unchanged
+        );
+        assert_eq!(
+            Action::Replaced {
+                line: "line".into(),
+                new: "new\n".into(),
+                pos: 3,
+                warnings: Vec::new(),
+            }
+            .into_unchanged(),
+            unchanged
+        );
+        assert_eq!(
+            Action::Flagged {
+                line: "line\n".into(),
+                pos: 3,
+                warnings: vec!["warning".into()],
+            }
+            .into_unchanged(),
+            unchanged
+        );
     }
 }
@@ -28,8 +28,15 @@ impl<'a> Candidate<'a> {
     }
 
     /// Apply the transformation based on the given context.
-    pub fn transform(self, ctx: &crate::ConversionContext) -> Option<String> {
-        self.inner.transform(ctx)
+    ///
+    /// Warnings about less reliable conversions are pushed into `warnings`,
+    /// prefixed with the link they are about.
+    pub fn transform(
+        self,
+        ctx: &crate::ConversionContext,
+        warnings: &mut Vec<String>,
+    ) -> Option<String> {
+        self.inner.transform(ctx, warnings)
     }
 }
 
@@ -96,15 +103,18 @@ impl<'a> CandidateInner<'a> {
         }
     }
 
-    fn transform(self, ctx: &crate::ConversionContext) -> Option<String> {
+    fn transform(
+        self,
+        ctx: &crate::ConversionContext,
+        warnings: &mut Vec<String>,
+    ) -> Option<String> {
         match self {
             Self::Long { header, name, link } => {
                 if ctx.options().is_ignored(name, link) {
                     return None;
                 }
 
-                let parts = crate::link_parts::link_parts(link, ctx.options()).ok()?;
-                let link = parts.transform(ctx);
+                let link = transform_link(link, ctx, warnings)?;
                 Some(format!("{h}{l}", h = header, l = link))
             }
             Self::Short { orig } => {
@@ -125,13 +135,12 @@ impl<'a> CandidateInner<'a> {
                             return cap.get(0).unwrap().as_str().to_string();
                         }
 
-                        let parts = match crate::link_parts::link_parts(link, ctx.options()) {
+                        let link = match transform_link(link, ctx, warnings) {
                             // UNWRAP: see above.
-                            Err(_) => return cap.get(0).unwrap().as_str().to_string(),
-                            Ok(p) => p,
+                            None => return cap.get(0).unwrap().as_str().to_string(),
+                            Some(l) => l,
                         };
 
-                        let link = parts.transform(ctx);
                         let mut res = format!("[{h}]", h = header);
                         if link != name {
                             res.push('(');
@@ -147,5 +156,77 @@ impl<'a> CandidateInner<'a> {
     }
 }
 
+/// Transforms a single link, returning `None` when it must be left as is.
+///
+/// Warnings about the link are pushed into `warnings`, prefixed with it.
+fn transform_link(
+    link: &Path,
+    ctx: &crate::ConversionContext,
+    warnings: &mut Vec<String>,
+) -> Option<String> {
+    let mut warn = |w: &str| warnings.push(format!("'{}': {}", link.display(), w));
+
+    let parts = crate::link_parts::link_parts(link, ctx.options()).ok()?;
+    let parts = match parts.with_facade(ctx.options()) {
+        Ok(parts) => parts,
+        Err(w) => {
+            warn(w);
+            return None;
+        }
+    };
+
+    if let Some(w) = parts.warning() {
+        warn(w);
+    }
+
+    if let Some(doc_dir) = ctx.options().doc_dir {
+        if parts.has_section() {
+            if let Some(w) = crate::sections::check_fragment(
+                doc_dir,
+                link,
+                ctx.assoc_parent().filter(|&ty| ty != "Self"),
+            ) {
+                warn(&w);
+            }
+        }
+    }
+
+    if let Some(w) = crate::link_parts::docs_rs_version_mismatch(link, ctx.options()) {
+        match ctx.options().version_mismatch() {
+            crate::VersionMismatch::Warn => warn(&w),
+            crate::VersionMismatch::Skip => {
+                warn(&format!("{}, left unchanged", w));
+                return None;
+            }
+        }
+    }
+
+    if parts.needs_assoc_parent() && ctx.assoc_parent().is_none() {
+        warn("Associated item link outside of a type or trait, left unchanged");
+        return None;
+    }
+
+    if !parts.is_impl_anchor() {
+        return Some(parts.transform(ctx));
+    }
+
+    match ctx.options().impl_anchors() {
+        crate::ImplAnchors::Keep => Some(parts.transform(ctx)),
+        crate::ImplAnchors::Target => {
+            let target = parts.transform_impl_target(ctx);
+            if target.is_some() {
+                warn("Impl anchor replaced by its target, check it is in scope");
+            } else {
+                warn("Impl anchor with an unknown target, left unchanged");
+            }
+            target
+        }
+        crate::ImplAnchors::Warn => {
+            warn("Impl anchors cannot be written as intra-doc links, left unchanged");
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests;
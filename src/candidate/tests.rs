@@ -151,7 +151,7 @@ fn candidate_transform() {
     fn check_transform(value: &str, target: &str, ctx: &ConversionContext) {
         let candidate = Candidate::from_line(value).unwrap();
         let candidate_clone = Candidate::from_line(value).unwrap();
-        let transform = candidate.transform(&ctx).unwrap();
+        let transform = candidate.transform(ctx, &mut Vec::new()).unwrap();
         assert_eq!(
             target, transform,
             "\n--> Value: {:#?}, candidate: {:#?}",
@@ -255,16 +255,22 @@ fn do_not_transform_primitive() {
 
     for value in long_primitive_links.iter() {
         let candidate = Candidate::from_line(value).unwrap();
-        assert!(candidate.transform(&ctx_dis).is_none());
+        assert!(candidate.transform(&ctx_dis, &mut Vec::new()).is_none());
         let candidate = Candidate::from_line(value).unwrap();
-        assert!(candidate.transform(&ctx_no_dis).is_none());
+        assert!(candidate.transform(&ctx_no_dis, &mut Vec::new()).is_none());
     }
 
     for &value in short_primitive_links.iter() {
         let candidate = Candidate::from_line(value).unwrap();
-        assert_eq!(value, candidate.transform(&ctx_dis).unwrap());
+        assert_eq!(
+            value,
+            candidate.transform(&ctx_dis, &mut Vec::new()).unwrap()
+        );
         let candidate = Candidate::from_line(value).unwrap();
-        assert_eq!(value, candidate.transform(&ctx_no_dis).unwrap());
+        assert_eq!(
+            value,
+            candidate.transform(&ctx_no_dis, &mut Vec::new()).unwrap()
+        );
     }
 }
 
@@ -2307,3 +2313,157 @@ const VALID_SHORT_LINKS: &[(&str, &str, &str)] = &[
         "[`Link`](regex::bytes#syntax)",
     ),
 ];
+
+#[test]
+fn impl_anchors() {
+    use crate::{ConversionContext, ConversionOptions, RawFileConfig};
+
+    fn check(mode: &str, value: &str, target: Option<&str>, warned: bool) {
+        let config: RawFileConfig =
+            toml::from_str(&format!("impl-anchors = \"{}\"", mode)).unwrap();
+        let config = config.finish(Path::new(".")).unwrap();
+        let mut ctx = ConversionContext::with_options(ConversionOptions {
+            ignored_links: &config,
+            ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+        });
+        ctx.set_current_type_block("Block".into());
+
+        let mut warnings = Vec::new();
+        let candidate = Candidate::from_line(value).unwrap();
+        assert_eq!(
+            candidate.transform(&ctx, &mut warnings).as_deref(),
+            target,
+            "{} -- {}",
+            mode,
+            value
+        );
+        assert_eq!(!warnings.is_empty(), warned, "{} -- {}", mode, value);
+    }
+
+    let link = "[`Display`]: struct.Foo.html#impl-Display-for-Foo";
+    check(
+        "keep",
+        link,
+        Some("[`Display`]: Foo#impl-Display-for-Foo"),
+        false,
+    );
+    check("target", link, Some("[`Display`]: Display"), true);
+    check("warn", link, None, true);
+
+    let link = "[`Display`]: ../struct.Foo.html#impl-Display";
+    check("target", link, Some("[`Display`]: Display"), true);
+
+    let link = "[`Foo`]: ../struct.Foo.html#impl-Foo";
+    check("target", link, Some("[`Foo`]: super::Foo"), true);
+
+    let link = "[`Foo`]: struct.Foo.html#impl-1";
+    check("target", link, Some("[`Foo`]: Foo"), true);
+
+    let link = "[`str`]: struct.Foo.html#deref-methods-str";
+    check("keep", link, Some("[`str`]: Foo#deref-methods-str"), false);
+    check("target", link, Some("[`str`]: str"), true);
+    check("warn", link, None, true);
+
+    let link = "[`str`]: struct.Foo.html#deref-methods";
+    check("target", link, None, true);
+
+    let link = "[Display](#impl-Display-for-Block) and [x](#method.x)";
+    check(
+        "keep",
+        link,
+        Some("[Display](#impl-Display-for-Block) and [x](Block::x())"),
+        false,
+    );
+    check("target", link, Some("[Display] and [x](Block::x())"), true);
+    check(
+        "warn",
+        link,
+        Some("[Display](#impl-Display-for-Block) and [x](Block::x())"),
+        true,
+    );
+
+    let link = "[Block](#impl)";
+    check("target", link, Some("[Block]"), true);
+
+    // Generic parameters are percent-encoded by rustdoc.
+    let link = "[`From`]: struct.Foo.html#impl-From%3CT%3E-for-Foo";
+    check(
+        "keep",
+        link,
+        Some("[`From`]: Foo#impl-From%3CT%3E-for-Foo"),
+        false,
+    );
+    check("target", link, Some("[`From`]: From"), true);
+    check("warn", link, None, true);
+
+    let link = "[`PartialEq`]: struct.Foo.html#impl-PartialEq%3CVec%3CU%3E%3E-for-Foo%3CT%3E";
+    check("target", link, Some("[`PartialEq`]: PartialEq"), true);
+
+    let link = "[`Foo`]: struct.Foo.html#impl-Foo%3CT%3E";
+    check("target", link, Some("[`Foo`]: Foo"), true);
+
+    // Sections that look like impl anchors but are not.
+    let link = "[`Foo`]: struct.Foo.html#implementation-notes";
+    check(
+        "warn",
+        link,
+        Some("[`Foo`]: Foo#implementation-notes"),
+        false,
+    );
+}
+
+#[test]
+fn version_mismatch() {
+    use crate::{ConversionContext, ConversionOptions, Dependency, RawFileConfig};
+
+    let deps = [
+        Dependency {
+            lib: "regex".into(),
+            name: "regex".into(),
+            version: Some(cargo_metadata::Version::new(1, 4, 2)),
+        },
+        Dependency {
+            lib: "rand".into(),
+            name: "rand".into(),
+            version: Some(cargo_metadata::Version::new(0, 7, 3)),
+        },
+    ];
+
+    let check = |mode: &str, value: &str, target: Option<&str>, warned: bool| {
+        let config: RawFileConfig =
+            toml::from_str(&format!("version-mismatch = \"{}\"", mode)).unwrap();
+        let config = config.finish(Path::new(".")).unwrap();
+        let ctx = ConversionContext::with_options(ConversionOptions {
+            ignored_links: &config,
+            dependencies: Some(&deps),
+            ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+        });
+
+        let mut warnings = Vec::new();
+        let candidate = Candidate::from_line(value).unwrap();
+        assert_eq!(
+            candidate.transform(&ctx, &mut warnings).as_deref(),
+            target,
+            "{} -- {}",
+            mode,
+            value
+        );
+        assert_eq!(!warnings.is_empty(), warned, "{} -- {}", mode, value);
+    };
+
+    let link = "[`Regex`]: https://docs.rs/regex/0.2.11/regex/struct.Regex.html";
+    check("warn", link, Some("[`Regex`]: regex::Regex"), true);
+    check("skip", link, None, true);
+
+    let link = "[`Regex`]: https://docs.rs/regex/1.0.0/regex/struct.Regex.html";
+    check("skip", link, Some("[`Regex`]: regex::Regex"), false);
+
+    let link = "[`Regex`]: https://docs.rs/regex/latest/regex/struct.Regex.html";
+    check("skip", link, Some("[`Regex`]: regex::Regex"), false);
+
+    let link = "[`Rng`]: https://docs.rs/rand/0.7.0/rand/trait.Rng.html";
+    check("skip", link, Some("[`Rng`]: rand::Rng"), false);
+
+    let link = "[`Rng`]: https://docs.rs/rand/0.8.0/rand/trait.Rng.html";
+    check("skip", link, None, true);
+}
@@ -46,8 +46,9 @@ pub struct CliArgs {
 
     /// config file to ignore some links when processing the files. See the
     /// README at https://github.com/poliorcetics/cargo-intraconv for an
-    /// example of it. When not provided, will try to read `intraconv.toml` by
-    /// default (will do nothing is not present).
+    /// example of it. When not provided, will try to read `intraconv.toml`
+    /// next to the manifest given with `--manifest-path` or in the current
+    /// directory (will do nothing is not present).
     #[argh(option, long = "ignore-file", short = 'i')]
     pub config_file: Option<PathBuf>,
 
@@ -67,6 +68,82 @@ pub struct CliArgs {
     #[argh(switch, short = 'q')]
     pub quiet: bool,
 
+    /// display the files skipped when searching directories, because of
+    /// `.gitignore` files or the configuration file for example.
+    #[argh(switch, short = 'v')]
+    pub verbose: bool,
+
+    /// warn about `#section` fragments that do not exist in the documentation
+    /// generated by `cargo doc` in the target directory. Run `cargo doc`
+    /// before using this option. Links to websites are not checked.
+    #[argh(switch)]
+    pub check_sections: bool,
+
+    /// package to convert, can be given several times. Accepts glob patterns
+    /// like `foo_*`.
+    #[argh(option, long = "package", short = 'p')]
+    pub packages: Vec<String>,
+
+    /// convert all the packages of the workspace.
+    #[argh(switch)]
+    pub workspace: bool,
+
+    /// package to leave out with `--workspace`, can be given several times.
+    /// Accepts glob patterns like `legacy_*`.
+    #[argh(option)]
+    pub exclude: Vec<String>,
+
+    /// path to the Cargo.toml of the workspace or package to convert,
+    /// instead of the one in the current directory.
+    #[argh(option)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// read the files to convert from a file, or from the standard input
+    /// with `-`. Paths are separated by newlines, or by NUL characters when
+    /// there is any. The crate of each file is found from its location.
+    #[argh(option)]
+    pub files_from: Option<PathBuf>,
+
+    /// only convert the `.rs` and `.md` files changed since the given git
+    /// revision, committed or not, under the current directory. Untracked
+    /// files that are not ignored are converted too.
+    #[argh(option)]
+    pub changed_since: Option<String>,
+
+    /// only convert the `.rs` and `.md` files staged in git, under the
+    /// current directory. The staged content is read and changes are only
+    /// applied to files without unstaged changes, which are then staged
+    /// again.
+    #[argh(switch)]
+    pub staged: bool,
+
+    /// with `--changed-since` or `--staged`, only change the lines touched
+    /// in the diff.
+    #[argh(switch)]
+    pub only_changed_lines: bool,
+
+    /// convert the content of the standard input and write the result to
+    /// the standard output, displaying the changes on the standard error.
+    /// Nothing is written on disk.
+    #[argh(switch)]
+    pub stdin: bool,
+
+    /// with `--stdin`, path of the file being converted, to find its crate,
+    /// its module and the links to ignore for it.
+    #[argh(option)]
+    pub stdin_filename: Option<PathBuf>,
+
+    /// write the converted files to this directory instead of modifying
+    /// them, mirroring their layout from the current directory (or from the
+    /// directory of `--manifest-path`), which it cannot contain. Only changed
+    /// files are written unless `--copy-unchanged` is given.
+    #[argh(option)]
+    pub out_dir: Option<PathBuf>,
+
+    /// with `--out-dir`, also write the files that have no changes.
+    #[argh(switch)]
+    pub copy_unchanged: bool,
+
     /// files to search links in. Directories will use the crate name from the
     /// Cargo.toml file when possible, exact paths will use the name given
     /// with the `-c` option.
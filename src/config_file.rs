@@ -9,11 +9,192 @@ impl FileConfig {
     pub fn is_ignored(&self, file: &Path, name: &str, value: &Path) -> bool {
         self.0.is_globally_ignored(name, value) || self.0.is_locally_ignored(file, name, value)
     }
+
+    /// How to treat `#impl-...` and `#deref-methods-...` anchors.
+    pub fn impl_anchors(&self) -> ImplAnchors {
+        self.0.impl_anchors
+    }
+
+    /// What to do with `docs.rs` links to a version incompatible with the one
+    /// in `Cargo.lock`.
+    pub fn version_mismatch(&self) -> VersionMismatch {
+        self.0.version_mismatch
+    }
+
+    /// Additional hosts whose links are favored, like `docs.rs`.
+    pub fn favored_hosts(&self) -> &[FavoredHost] {
+        &self.0.favored
+    }
+
+    /// Crates to use when not in a cargo directory.
+    pub fn crates(&self) -> &[CrateMapping] {
+        &self.0.crates
+    }
+
+    /// Why the file at `path` (absolute) must not be converted when walking
+    /// directories, if it must not be.
+    pub fn exclusion_reason(&self, path: &Path) -> Option<&'static str> {
+        if self.0.exclude.iter().any(|p| p.matches_path(path)) {
+            Some("excluded in the configuration file")
+        } else if !self.0.include.is_empty() && !self.0.include.iter().any(|p| p.matches_path(path))
+        {
+            Some("not included in the configuration file")
+        } else {
+            None
+        }
+    }
+}
+
+/// A host serving documentation generated by `rustdoc`, whose links are
+/// transformed like those to `docs.rs`.
+///
+/// Form:
+///
+/// ```toml
+/// [[favored]]
+/// # Can contain a path: "example.com/docs".
+/// host = "docs.internal.example"
+/// # Segments between the host and the root of the documentation, the one
+/// # containing a directory for each crate.
+/// pattern = "{crate}/{version}"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct FavoredHost {
+    host: String,
+
+    #[serde(default)]
+    pattern: String,
+}
+
+/// A segment of the URL to a favored host.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HostSegment<'a> {
+    /// `{crate}`: the name of the documented crate.
+    Crate,
+    /// `{version}`: the version of the documented crate, any value.
+    Version,
+    /// `{channel}`: the release channel (`nightly`, ...), any value.
+    Channel,
+    /// Must be matched exactly.
+    Literal(&'a str),
+}
+
+impl FavoredHost {
+    /// Domain name of the host, without any path.
+    pub fn domain(&self) -> &str {
+        self.host.split('/').next().unwrap_or("")
+    }
+
+    /// Segments expected between the domain name and the root of the
+    /// documentation: the path of the host and then the pattern.
+    pub fn segments(&self) -> impl Iterator<Item = HostSegment<'_>> {
+        self.host
+            .split('/')
+            .skip(1)
+            .chain(self.pattern.split('/'))
+            .filter(|s| !s.is_empty())
+            .map(|s| match s {
+                "{crate}" => HostSegment::Crate,
+                "{version}" => HostSegment::Version,
+                "{channel}" => HostSegment::Channel,
+                _ => HostSegment::Literal(s),
+            })
+    }
+}
+
+/// Directories holding crates, for when `cargo metadata` cannot be used (with
+/// Bazel for example).
+///
+/// Form:
+///
+/// ```toml
+/// [[crates]]
+/// # Glob matching the directories of crates, from the directory of the
+/// # configuration file.
+/// dir = "libs/*"
+/// # Name of the crates, defaults to the name of each matched directory.
+/// name = "my_crate"
+/// # Root file of the crates, from each matched directory.
+/// root = "src/lib.rs"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct CrateMapping {
+    dir: String,
+
+    #[serde(default)]
+    name: Option<String>,
+
+    #[serde(default = "CrateMapping::default_root")]
+    root: PathBuf,
+}
+
+impl CrateMapping {
+    fn default_root() -> PathBuf {
+        PathBuf::from("src/lib.rs")
+    }
+
+    /// Glob pattern matching the directories of the crates.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// Name of the crate in `dir`, a directory matched by the pattern.
+    pub fn name(&self, dir: &Path) -> Option<String> {
+        match &self.name {
+            Some(name) => Some(name.clone()),
+            None => dir.file_name()?.to_str().map(String::from),
+        }
+    }
+
+    /// Root file of the crate in `dir`, a directory matched by the pattern.
+    pub fn root(&self, dir: &Path) -> PathBuf {
+        dir.join(&self.root)
+    }
+}
+
+/// What to do with links to `#impl-...` and `#deref-methods-...` anchors,
+/// which have no intra-doc link equivalent.
+///
+/// Form:
+///
+/// ```toml
+/// impl-anchors = "keep" # or "target" or "warn"
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImplAnchors {
+    /// Link to the item and keep the anchor as a fragment:
+    /// `Foo#impl-Display-for-Foo`.
+    #[default]
+    Keep,
+    /// Link to the implemented trait or to the deref target instead:
+    /// `Display`, `str`, ...
+    Target,
+    /// Leave the link as is and warn about it.
+    Warn,
+}
+
+/// What to do with `docs.rs` links to a version of a dependency that is not
+/// compatible with the one in `Cargo.lock`.
+///
+/// Form:
+///
+/// ```toml
+/// version-mismatch = "warn" # or "skip"
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionMismatch {
+    /// Transform the link and warn about it.
+    #[default]
+    Warn,
+    /// Leave the link as is and warn about it.
+    Skip,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Default, Deserialize)]
 pub struct RawFileConfig {
-    #[serde(rename = "ignore")]
+    #[serde(rename = "ignore", default)]
     /// Form:
     ///
     /// ```toml
@@ -22,6 +203,34 @@ pub struct RawFileConfig {
     /// ```
     globals: HashMap<String, BTreeSet<PathBuf>>,
 
+    #[serde(rename = "impl-anchors", default)]
+    impl_anchors: ImplAnchors,
+
+    #[serde(rename = "version-mismatch", default)]
+    version_mismatch: VersionMismatch,
+
+    #[serde(default)]
+    favored: Vec<FavoredHost>,
+
+    #[serde(default)]
+    crates: Vec<CrateMapping>,
+
+    /// Form:
+    ///
+    /// ```toml
+    /// # Relative to the current directory. When present, only the files
+    /// # matching one of the patterns are converted in directories.
+    /// include = ["src/**/*.rs"]
+    /// # Files matching one of the patterns are never converted in
+    /// # directories, even when included.
+    /// exclude = ["**/generated/**"]
+    /// ```
+    #[serde(default, deserialize_with = "deserialize_patterns")]
+    include: Vec<glob::Pattern>,
+
+    #[serde(default, deserialize_with = "deserialize_patterns")]
+    exclude: Vec<glob::Pattern>,
+
     /// Form:
     ///
     /// ```toml
@@ -39,7 +248,11 @@ pub struct RawFileConfig {
 }
 
 impl RawFileConfig {
-    pub fn finish(mut self) -> std::io::Result<FileConfig> {
+    /// Resolves the relative paths of the configuration from `dir`, the
+    /// directory of the configuration file or the current one for a file given
+    /// with `-i`.
+    pub fn finish(mut self, dir: &Path) -> std::io::Result<FileConfig> {
+        let dir = std::env::current_dir()?.join(dir);
         let mut canonicalized = self.per_file.clone();
         canonicalized.retain(|k, _| {
             let mut c = k.components();
@@ -50,11 +263,27 @@ impl RawFileConfig {
             // Testing if the path has two components without checking it in
             // its entirety.
             if c.next().is_some() && c.next().is_some() {
-                let canon = k.canonicalize()?;
+                let canon = dir.join(k).canonicalize()?;
                 canonicalized.insert(canon, v);
             }
         }
         self.per_file = canonicalized;
+
+        // Matching absolute paths avoids depending on the current directory
+        // when files are converted.
+        let dir = glob::Pattern::escape(&dir.to_string_lossy());
+        for pattern in self.include.iter_mut().chain(self.exclude.iter_mut()) {
+            if !Path::new(pattern.as_str()).is_absolute() {
+                *pattern = glob::Pattern::new(&format!("{}/{}", dir, pattern.as_str()))
+                    .expect("An escaped directory followed by a valid pattern is valid");
+            }
+        }
+        for mapping in self.crates.iter_mut() {
+            if !Path::new(&mapping.dir).is_absolute() {
+                mapping.dir = format!("{}/{}", dir, mapping.dir);
+            }
+        }
+
         Ok(FileConfig(self))
     }
 
@@ -81,5 +310,42 @@ fn contains_ignored(map: &HashMap<String, BTreeSet<PathBuf>>, name: &str, value:
     // This will help match links that are also code blocks.
     let name = name.trim_matches('`');
 
-    map.get(name).map_or(false, |values| values.contains(value))
+    map.get(name).is_some_and(|values| values.contains(value))
+}
+
+fn deserialize_patterns<'de, D>(deserializer: D) -> Result<Vec<glob::Pattern>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|p| glob::Pattern::new(p).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[test]
+fn test_finish_from_dir() {
+    let fixture = crate::fixture(&[("krate/src/lib.rs", ""), ("krate/src/gen.rs", "")]);
+    let dir = fixture.path().canonicalize().unwrap();
+
+    let config: RawFileConfig = toml::from_str(
+        r#"
+        exclude = ["krate/src/gen.rs"]
+
+        ["krate/src/lib.rs"]
+        "Vec" = ["struct.Vec.html"]
+        "#,
+    )
+    .unwrap();
+    let config = config.finish(&dir).unwrap();
+
+    let vec = Path::new("struct.Vec.html");
+    assert!(config.is_ignored(&dir.join("krate/src/lib.rs"), "Vec", vec));
+    assert!(!config.is_ignored(&dir.join("krate/src/gen.rs"), "Vec", vec));
+    assert!(config
+        .exclusion_reason(&dir.join("krate/src/gen.rs"))
+        .is_some());
+    assert!(config
+        .exclusion_reason(&dir.join("krate/src/lib.rs"))
+        .is_none());
 }
@@ -44,7 +44,7 @@ lazy_static! {
         r"(?P<header>\s*(?://[!/]\s*)?\[`?(?P<name>.+?)`?\]:\s*)",
         // The special case for 'http(s):' is to avoid catching links with a
         // '::' by putting ':' in the regex: they are already intra-doc links.
-        r"(?P<link>(?:https?:)?[a-zA-Z0-9_#/\-\.]+)",
+        r"(?P<link>(?:https?:)?[a-zA-Z0-9_#/%\-\.]+)",
         r"\n?$",
     ))
     .unwrap();
@@ -63,7 +63,7 @@ lazy_static! {
     /// To be used with `::regex::Regex::captures_iter`.
     pub static ref LINK_TO_TREAT_SHORT: Regex = Regex::new(concat!(
         r"\[(?P<header>`?(?P<name>.+?)`?)\]",
-        r"\((?P<link>(?:https?:)?[a-zA-Z0-9_#/\-\.]+)\)",
+        r"\((?P<link>(?:https?:)?[a-zA-Z0-9_#/%\-\.]+)\)",
     )).unwrap();
 
     /// Non-capturing regex to check if something is exactly an item type as
@@ -91,7 +91,7 @@ lazy_static! {
 
 pub const RUST_IDENTIFIER: &str = r"(?:[a-zA-Z_][a-zA-Z0-9_]*)";
 
-pub const HTML_SECTION: &str = r"(?:#[a-zA-Z0-9_\-\.]+)";
+pub const HTML_SECTION: &str = r"(?:#[a-zA-Z0-9_%\-\.]+)";
 
 #[cfg(test)]
 use crate::{ConversionContext, ConversionOptions, FileConfig, Krate};
@@ -108,6 +108,13 @@ lazy_static::lazy_static! {
         favored_links: true,
         ignored_links: &NO_IGNORE,
         current_path: Path::new(""),
+        doc_dir: None,
+        html_root_url: None,
+        doc_roots: &[],
+        dependencies: None,
+        facade: crate::Facade::Std,
+        workspace_crates: &[],
+        module_depth: 0,
     };
 
     pub static ref OPTS_KRATE_NO_DIS_NO_FAV: ConversionOptions<'static> = ConversionOptions {
@@ -116,6 +123,13 @@ lazy_static::lazy_static! {
         favored_links: false,
         ignored_links: &NO_IGNORE,
         current_path: Path::new(""),
+        doc_dir: None,
+        html_root_url: None,
+        doc_roots: &[],
+        dependencies: None,
+        facade: crate::Facade::Std,
+        workspace_crates: &[],
+        module_depth: 0,
     };
 
     pub static ref OPTS_KRATE_NO_DIS_BUT_FAV: ConversionOptions<'static> = ConversionOptions {
@@ -124,6 +138,13 @@ lazy_static::lazy_static! {
         favored_links: true,
         ignored_links: &NO_IGNORE,
         current_path: Path::new(""),
+        doc_dir: None,
+        html_root_url: None,
+        doc_roots: &[],
+        dependencies: None,
+        facade: crate::Facade::Std,
+        workspace_crates: &[],
+        module_depth: 0,
     };
 
     pub static ref OPTS_KRATE_DIS_NO_FAV: ConversionOptions<'static> = ConversionOptions {
@@ -132,6 +153,13 @@ lazy_static::lazy_static! {
         favored_links: false,
         ignored_links: &NO_IGNORE,
         current_path: Path::new(""),
+        doc_dir: None,
+        html_root_url: None,
+        doc_roots: &[],
+        dependencies: None,
+        facade: crate::Facade::Std,
+        workspace_crates: &[],
+        module_depth: 0,
     };
 
     pub static ref CTX_KRATE_DIS_AND_FAV: ConversionContext<'static> = ConversionContext::with_options(OPTS_KRATE_DIS_AND_FAV.clone());
@@ -143,6 +171,45 @@ lazy_static::lazy_static! {
     pub static ref CTX_KRATE_DIS_NO_FAV: ConversionContext<'static> = ConversionContext::with_options(OPTS_KRATE_DIS_NO_FAV.clone());
 }
 
+/// Creates a temporary directory containing `files`, given as pairs of paths
+/// relative to it and contents.
+///
+/// The directory is removed when the returned value is dropped, even when the
+/// test fails.
+#[cfg(test)]
+pub fn fixture(files: &[(&str, &str)]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (file, content) in files {
+        let file = dir.path().join(file);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+    }
+    dir
+}
+
+/// Runs `git` with `args` in `dir`, for tests.
+#[cfg(test)]
+pub fn run_git(dir: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// Like `fixture`, with `files` committed in a new git repository.
+#[cfg(test)]
+pub fn git_fixture(files: &[(&str, &str)]) -> tempfile::TempDir {
+    let dir = fixture(files);
+    run_git(dir.path(), &["init", "--quiet"]);
+    run_git(dir.path(), &["add", "--all"]);
+    run_git(dir.path(), &["commit", "--quiet", "--message", "Initial"]);
+    dir
+}
+
 #[test]
 fn link_to_treat_long_matching() {
     // Testing spacing.
@@ -289,6 +356,8 @@ fn html_section() {
     // the same. It is up to functions with more information to make the
     // difference.
     assert!(reg.is_match("#fn.item"));
+    // Percent-encoded, as in `#impl-From%3CT%3E-for-Foo`.
+    assert!(reg.is_match("#impl-From%3CT%3E"));
 
     assert!(!reg.is_match("#"));
     assert!(!reg.is_match("abc"));
@@ -15,7 +15,7 @@
 #[macro_export]
 macro_rules! continue_error {
     ($value:expr, $($arg:tt)*) => {
-        $crate::__error!(continue, $value, $($arg)*);
+        $crate::__error!(continue, $value, $($arg)*)
     };
 }
 
@@ -26,7 +26,7 @@ macro_rules! continue_error {
 #[macro_export]
 macro_rules! return_error {
     ($value:expr, $($arg:tt)*) => {
-        $crate::__error!(return, $value, $($arg)*);
+        $crate::__error!(return, $value, $($arg)*)
     };
 }
 
@@ -37,7 +37,7 @@ macro_rules! return_error {
 #[macro_export]
 macro_rules! code_error {
     ($code: expr, $value:expr, $($arg:tt)*) => {
-        $crate::__error!(::std::process::exit($code), $value, $($arg)*);
+        $crate::__error!(::std::process::exit($code), $value, $($arg)*)
     };
 }
 
@@ -77,7 +77,7 @@ fn continue_error() {
     // Should always continue, never reaching the false.
     for i in 0..10 {
         continue_error!(TestResult::Err(i), "");
-        assert!(false, "Should never be reached");
+        unreachable!("Should never be reached");
     }
 }
 
@@ -85,7 +85,7 @@ fn continue_error() {
 fn return_error() {
     fn early_return() {
         return_error!(TestResult::Err(4), "");
-        assert!(false, "Should never be reached");
+        unreachable!("Should never be reached");
     }
 
     early_return();
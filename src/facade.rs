@@ -0,0 +1,204 @@
+//! Knowledge of where the items of `std` are really defined.
+//!
+//! `std` re-exports most of `core` and `alloc`. A `#![no_std]` crate cannot
+//! link to `std::option::Option` but `core::option::Option` is the same item,
+//! so links to `std` can be rewritten when the item is not `std` only.
+
+/// Standard library crates, ordered by what they make available: a crate with
+/// `std` has access to everything in `alloc` and `core` too.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub enum Facade {
+    /// `core`, always available.
+    Core,
+    /// `alloc`, available to `#![no_std]` crates with `extern crate alloc;`.
+    Alloc,
+    /// `std`, available unless the crate is `#![no_std]`.
+    #[default]
+    Std,
+}
+
+impl Facade {
+    /// The facade crate named `name`, if any.
+    pub fn from_crate(name: &str) -> Option<Self> {
+        match name {
+            "core" => Some(Self::Core),
+            "alloc" => Some(Self::Alloc),
+            "std" => Some(Self::Std),
+            _ => None,
+        }
+    }
+
+    /// Name of the crate.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Core => "core",
+            Self::Alloc => "alloc",
+            Self::Std => "std",
+        }
+    }
+}
+
+/// Crate where the items of `std` are defined, by path inside `std`.
+///
+/// Macros end with a `!` since some share their name with a module (`env!`
+/// and `std::env`). The most specific path wins and paths that are not listed
+/// are considered to be only in `std`.
+const STD_ITEMS: &[(&str, Facade)] = &[
+    // Modules.
+    ("alloc", Facade::Core),
+    ("alloc::Global", Facade::Alloc),
+    ("alloc::alloc", Facade::Alloc),
+    ("alloc::alloc_zeroed", Facade::Alloc),
+    ("alloc::dealloc", Facade::Alloc),
+    ("alloc::handle_alloc_error", Facade::Alloc),
+    ("alloc::realloc", Facade::Alloc),
+    ("alloc::System", Facade::Std),
+    ("any", Facade::Core),
+    ("array", Facade::Core),
+    ("ascii", Facade::Core),
+    ("borrow", Facade::Core),
+    ("borrow::Cow", Facade::Alloc),
+    ("borrow::ToOwned", Facade::Alloc),
+    ("boxed", Facade::Alloc),
+    ("cell", Facade::Core),
+    ("char", Facade::Core),
+    ("clone", Facade::Core),
+    ("cmp", Facade::Core),
+    ("collections", Facade::Alloc),
+    ("collections::HashMap", Facade::Std),
+    ("collections::HashSet", Facade::Std),
+    ("collections::hash_map", Facade::Std),
+    ("collections::hash_set", Facade::Std),
+    ("convert", Facade::Core),
+    ("default", Facade::Core),
+    ("error", Facade::Core),
+    ("f32", Facade::Core),
+    ("f64", Facade::Core),
+    ("ffi", Facade::Core),
+    ("ffi::CString", Facade::Alloc),
+    ("ffi::OsStr", Facade::Std),
+    ("ffi::OsString", Facade::Std),
+    ("fmt", Facade::Core),
+    ("fmt::format", Facade::Alloc),
+    ("future", Facade::Core),
+    ("hash", Facade::Core),
+    ("hash::DefaultHasher", Facade::Std),
+    ("hash::RandomState", Facade::Std),
+    ("hint", Facade::Core),
+    ("i8", Facade::Core),
+    ("i16", Facade::Core),
+    ("i32", Facade::Core),
+    ("i64", Facade::Core),
+    ("i128", Facade::Core),
+    ("isize", Facade::Core),
+    ("iter", Facade::Core),
+    ("marker", Facade::Core),
+    ("mem", Facade::Core),
+    ("net", Facade::Core),
+    ("net::Incoming", Facade::Std),
+    ("net::Shutdown", Facade::Std),
+    ("net::TcpListener", Facade::Std),
+    ("net::TcpStream", Facade::Std),
+    ("net::ToSocketAddrs", Facade::Std),
+    ("net::UdpSocket", Facade::Std),
+    ("num", Facade::Core),
+    ("ops", Facade::Core),
+    ("option", Facade::Core),
+    ("panic", Facade::Core),
+    ("panic::catch_unwind", Facade::Std),
+    ("panic::panic_any", Facade::Std),
+    ("panic::resume_unwind", Facade::Std),
+    ("panic::set_hook", Facade::Std),
+    ("panic::take_hook", Facade::Std),
+    ("pin", Facade::Core),
+    ("primitive", Facade::Core),
+    ("ptr", Facade::Core),
+    ("rc", Facade::Alloc),
+    ("result", Facade::Core),
+    ("slice", Facade::Core),
+    ("str", Facade::Core),
+    ("string", Facade::Alloc),
+    ("sync", Facade::Std),
+    ("sync::Arc", Facade::Alloc),
+    ("sync::Weak", Facade::Alloc),
+    ("sync::atomic", Facade::Core),
+    ("task", Facade::Core),
+    ("time", Facade::Std),
+    ("time::Duration", Facade::Core),
+    ("time::TryFromFloatSecsError", Facade::Core),
+    ("u8", Facade::Core),
+    ("u16", Facade::Core),
+    ("u32", Facade::Core),
+    ("u64", Facade::Core),
+    ("u128", Facade::Core),
+    ("usize", Facade::Core),
+    ("vec", Facade::Alloc),
+    // Macros.
+    ("assert!", Facade::Core),
+    ("assert_eq!", Facade::Core),
+    ("assert_ne!", Facade::Core),
+    ("cfg!", Facade::Core),
+    ("column!", Facade::Core),
+    ("compile_error!", Facade::Core),
+    ("concat!", Facade::Core),
+    ("debug_assert!", Facade::Core),
+    ("debug_assert_eq!", Facade::Core),
+    ("debug_assert_ne!", Facade::Core),
+    ("env!", Facade::Core),
+    ("file!", Facade::Core),
+    ("format!", Facade::Alloc),
+    ("format_args!", Facade::Core),
+    ("include!", Facade::Core),
+    ("include_bytes!", Facade::Core),
+    ("include_str!", Facade::Core),
+    ("line!", Facade::Core),
+    ("matches!", Facade::Core),
+    ("module_path!", Facade::Core),
+    ("option_env!", Facade::Core),
+    ("panic!", Facade::Core),
+    ("stringify!", Facade::Core),
+    ("todo!", Facade::Core),
+    ("unimplemented!", Facade::Core),
+    ("unreachable!", Facade::Core),
+    ("vec!", Facade::Alloc),
+    ("write!", Facade::Core),
+    ("writeln!", Facade::Core),
+];
+
+/// Crate where the item at `path` in `std` (like `option::Option` or
+/// `vec!`) is defined.
+pub fn std_location(path: &str) -> Facade {
+    STD_ITEMS
+        .iter()
+        .filter(|(item, _)| {
+            path == *item
+                || path
+                    .strip_prefix(item)
+                    .is_some_and(|rest| rest.starts_with("::"))
+        })
+        .max_by_key(|(item, _)| item.len())
+        .map_or(Facade::Std, |&(_, facade)| facade)
+}
+
+#[test]
+fn test_std_location() {
+    assert_eq!(std_location("option"), Facade::Core);
+    assert_eq!(std_location("option::Option"), Facade::Core);
+    assert_eq!(std_location("option::Option::map"), Facade::Core);
+    assert_eq!(std_location("vec::Vec"), Facade::Alloc);
+    assert_eq!(std_location("vec!"), Facade::Alloc);
+    assert_eq!(std_location("collections::BTreeMap"), Facade::Alloc);
+    assert_eq!(std_location("collections::HashMap"), Facade::Std);
+    assert_eq!(std_location("collections::hash_map::Entry"), Facade::Std);
+    assert_eq!(std_location("sync::Arc"), Facade::Alloc);
+    assert_eq!(std_location("sync::Mutex"), Facade::Std);
+    assert_eq!(std_location("sync::atomic::AtomicUsize"), Facade::Core);
+    assert_eq!(std_location("env!"), Facade::Core);
+    assert_eq!(std_location("env"), Facade::Std);
+    assert_eq!(std_location("println!"), Facade::Std);
+    assert_eq!(std_location("io::Read"), Facade::Std);
+    assert_eq!(std_location(""), Facade::Std);
+
+    // Not a prefix of the module.
+    assert_eq!(std_location("optional"), Facade::Std);
+}
@@ -1,29 +1,1188 @@
 // Code originally from:
 // https://github.com/deadlinks/cargo-deadlinks/blob/5af27cd5b4a2ce9c21b38053461ae007e645192f/src/main.rs#L130-L174
-use cargo_metadata::MetadataCommand;
+use crate::{Dependency, DocRoot, Facade, FileConfig};
+use cargo_metadata::{CargoOpt, DependencyKind, Metadata, MetadataCommand, Node, Package, Target};
+use regex::Regex;
 
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-pub fn crate_and_src() -> impl Iterator<Item = (String, PathBuf)> {
-    let manifest = crate::code_error!(
-        1,
-        MetadataCommand::new().no_deps().exec(),
-        "This is not a cargo directory, pass the files explicitly"
-    );
+/// A cargo target, whose files are converted together with the same crate
+/// name.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CrateTarget {
+    /// Name of the crate of the target, as used in paths.
+    pub name: String,
+
+    /// Name of the package of the target, as given to `-p`.
+    pub package: String,
+
+    /// `true` for libraries, which other crates can depend on.
+    pub is_lib: bool,
+
+    /// `true` for tests, examples and benches, which can use the
+    /// dev-dependencies of their package.
+    pub is_dev: bool,
+
+    /// Root file of the target, like `src/lib.rs` or `examples/demo.rs`.
+    pub root: PathBuf,
+
+    /// Directory containing the manifest of the package of the target.
+    pub package_dir: PathBuf,
+
+    /// Files of the target, sorted. They are all in the directory of `root`.
+    pub files: Vec<PathBuf>,
+}
+
+impl CrateTarget {
+    /// Directory of the root file, where the modules of the crate start.
+    pub fn root_dir(&self) -> &Path {
+        self.root.parent().expect("A root file always has a parent")
+    }
+
+    /// Build scripts are at the root of their package, they only own their
+    /// root file.
+    pub fn is_build_script(&self) -> bool {
+        self.root.file_name() == Some("build.rs".as_ref()) && self.root_dir() == self.package_dir
+    }
+}
+
+/// Finds all the targets of the packages in the current workspace (libraries,
+/// binaries, examples, tests, benches and build scripts) and their files.
+///
+/// Modules of a crate are in the directory of its root file so a file is
+/// given to the target whose root is in its closest parent directory,
+/// libraries first (`src/main.rs` is a binary but `src/mod1.rs` is part of
+/// the library). Build scripts only own their own file since they are at the
+/// root of the package.
+///
+/// When `cargo metadata` fails, the crates declared in `intraconv.toml` are
+/// used instead, see `mapped_targets`.
+///
+/// Files are found as described in `assign_files`.
+pub fn crate_targets(
+    manifest_path: Option<&Path>,
+    config: &FileConfig,
+    verbose: bool,
+) -> cargo_metadata::Result<Vec<CrateTarget>> {
+    let manifest = match metadata_command(manifest_path).no_deps().exec() {
+        Ok(manifest) => manifest,
+        Err(_) if !config.crates().is_empty() => return Ok(mapped_targets(config, verbose)),
+        Err(err) => return Err(err),
+    };
+
+    let mut targets: Vec<_> = manifest
+        .packages
+        .iter()
+        .flat_map(|package| {
+            let package_dir = package
+                .manifest_path
+                .parent()
+                .expect("A Cargo.toml cannot be the root")
+                .to_path_buf();
+
+            package.targets.iter().map(move |target| CrateTarget {
+                name: target.name.replace("-", "_"),
+                package: package.name.clone(),
+                is_lib: is_lib(target),
+                is_dev: target
+                    .kind
+                    .iter()
+                    .any(|k| matches!(k.as_str(), "test" | "example" | "bench")),
+                root: target.src_path.clone(),
+                package_dir: package_dir.clone(),
+                files: Vec::new(),
+            })
+        })
+        .collect();
+
+    assign_files(&mut targets, &manifest.target_directory, config, verbose);
+    Ok(targets)
+}
+
+/// Finds the crates declared with `[[crates]]` in `intraconv.toml`, for
+/// directories that are not handled by cargo (with Bazel for example).
+///
+/// They are all considered to be libraries.
+pub fn mapped_targets(config: &FileConfig, verbose: bool) -> Vec<CrateTarget> {
+    let mut targets = Vec::new();
+
+    for mapping in config.crates() {
+        let dirs = crate::continue_error!(
+            glob::glob(mapping.dir()),
+            "Invalid crate directory pattern '{}'",
+            mapping.dir()
+        );
+
+        for dir in dirs.filter_map(Result::ok).filter(|d| d.is_dir()) {
+            let dir =
+                crate::continue_error!(dir.canonicalize(), "Failed to canonicalize '{:?}'", dir);
+            let name = match mapping.name(&dir) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let root = mapping.root(&dir);
+            if !root.is_file() {
+                eprintln!(
+                    "Root file '{}' of crate '{}' not found, skipping it",
+                    root.display(),
+                    name
+                );
+                continue;
+            }
+
+            targets.push(CrateTarget {
+                name: name.replace("-", "_"),
+                package: name,
+                is_lib: true,
+                is_dev: false,
+                root,
+                package_dir: dir,
+                files: Vec::new(),
+            });
+        }
+    }
+
+    assign_files(&mut targets, Path::new(""), config, verbose);
+    targets
+}
+
+/// Finds the files of the targets, leaving out those in `target_dir`.
+///
+/// Files ignored by `.gitignore` or `.ignore` files and those left out by the
+/// `include` and `exclude` keys of the configuration file are skipped, and
+/// displayed when `verbose` is set. Root files are only skipped because of
+/// the configuration file.
+fn assign_files(
+    targets: &mut [CrateTarget],
+    target_dir: &Path,
+    config: &FileConfig,
+    verbose: bool,
+) {
+    let mut dirs: Vec<_> = targets
+        .iter()
+        .filter(|t| !t.is_build_script())
+        .map(|t| t.root_dir().to_path_buf())
+        .collect();
+    dirs.sort();
+    // Nested directories are walked with their parent.
+    dirs.dedup_by(|dir, parent| dir.starts_with(parent));
+
+    let mut skipped = BTreeSet::new();
+    let mut files = BTreeSet::new();
+    for dir in &dirs {
+        files.extend(rust_files(dir, target_dir, verbose, &mut skipped));
+    }
+    files.extend(targets.iter().map(|t| t.root.clone()));
+
+    files.retain(|file| match config.exclusion_reason(file) {
+        Some(reason) => {
+            skipped.insert((file.clone(), reason.into()));
+            false
+        }
+        None => true,
+    });
+
+    if verbose {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        for (file, reason) in skipped {
+            eprintln!(
+                "Skipped '{}': {}",
+                file.strip_prefix(&current_dir).unwrap_or(&file).display(),
+                reason
+            );
+        }
+    }
+
+    for file in files {
+        if let Some(owner) = owning_target(targets, &file) {
+            targets[owner].files.push(file);
+        }
+    }
+}
+
+/// Index of the target `file` (absolute) is part of, see `crate_targets`.
+///
+/// Several targets can have their root in the same directory, like
+/// `src/bin/a.rs` and `src/bin/b.rs`: files in `src/bin/a/` are then given to
+/// the target `a`.
+///
+/// This does not check if the file is skipped, only where it is.
+pub fn owning_target(targets: &[CrateTarget], file: &Path) -> Option<usize> {
+    targets.iter().position(|t| t.root == file).or_else(|| {
+        targets
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !t.is_build_script() && file.starts_with(t.root_dir()))
+            .max_by_key(|(idx, t)| {
+                let in_stem_dir = file
+                    .strip_prefix(t.root_dir())
+                    .ok()
+                    .and_then(|rest| rest.components().next())
+                    .map(|c| c.as_os_str())
+                    == t.root.file_stem();
+
+                (
+                    t.root_dir().components().count(),
+                    in_stem_dir,
+                    t.is_lib,
+                    std::cmp::Reverse(*idx),
+                )
+            })
+            .map(|(idx, _)| idx)
+    })
+}
+
+/// Reads a list of paths separated by newlines, or by NUL characters when
+/// there is any, from `source` or from the standard input when it is `-`.
+pub fn read_file_list(source: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let content = if source.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(source)?
+    };
+
+    Ok(parse_file_list(&content))
+}
+
+fn parse_file_list(content: &str) -> Vec<PathBuf> {
+    let paths: Box<dyn Iterator<Item = &str>> = if content.contains('\0') {
+        Box::new(content.split('\0'))
+    } else {
+        Box::new(content.lines())
+    };
+
+    paths.filter(|p| !p.is_empty()).map(PathBuf::from).collect()
+}
+
+/// Finds the Rust files in `dir`, outside of `target_dir`, respecting
+/// `.gitignore` and `.ignore` files.
+///
+/// Symbolic links are followed but loops are detected and skipped, like
+/// unreadable entries: they are added to `skipped` with the reason. When
+/// `report_ignored` is set, the Rust files and directories left out by the
+/// ignore files are added too.
+fn rust_files(
+    dir: &Path,
+    target_dir: &Path,
+    report_ignored: bool,
+    skipped: &mut BTreeSet<(PathBuf, String)>,
+) -> Vec<PathBuf> {
+    let in_target_dir = {
+        let target_dir = target_dir.to_path_buf();
+        move |path: &Path| !target_dir.as_os_str().is_empty() && path.starts_with(&target_dir)
+    };
+    let walker = ignore::WalkBuilder::new(dir)
+        .hidden(false)
+        .require_git(false)
+        .follow_links(true)
+        .filter_entry({
+            let in_target_dir = in_target_dir.clone();
+            move |e| !in_target_dir(e.path())
+        })
+        .build();
+
+    let mut files = Vec::new();
+    // The walker does not give the entries it ignores: they are the children
+    // of the directories it entered that it never gave.
+    let mut seen = HashSet::new();
+    let mut children = Vec::new();
+    for entry in walker {
+        match entry {
+            Ok(entry) => {
+                let file_type = entry.file_type();
+                if report_ignored {
+                    if file_type.is_some_and(|t| t.is_dir()) {
+                        let dir_children = std::fs::read_dir(entry.path()).into_iter().flatten();
+                        children.extend(dir_children.filter_map(Result::ok).map(|e| e.path()));
+                    }
+                    seen.insert(entry.path().to_path_buf());
+                }
+
+                let is_file = file_type.is_some_and(|t| t.is_file());
+                if is_file && entry.path().extension() == Some("rs".as_ref()) {
+                    files.push(entry.into_path());
+                }
+            }
+            Err(err) => {
+                let path = error_path(&err).unwrap_or(dir).to_path_buf();
+                seen.insert(path.clone());
+                skipped.insert((path, err.to_string()));
+            }
+        }
+    }
+
+    for child in children {
+        let is_reported = child.is_dir() || child.extension() == Some("rs".as_ref());
+        if is_reported && !seen.contains(&child) && !in_target_dir(&child) {
+            skipped.insert((child, "ignored by a `.gitignore` or `.ignore` file".into()));
+        }
+    }
+
+    files
+}
+
+/// Path an error of the walker is about, if known.
+fn error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        _ => None,
+    }
+}
+
+/// `cargo metadata` for the workspace of `manifest_path`, or the one of the
+/// current directory.
+pub fn metadata_command(manifest_path: Option<&Path>) -> MetadataCommand {
+    let mut cmd = MetadataCommand::new();
+    if let Some(path) = manifest_path {
+        cmd.manifest_path(path);
+    }
+    cmd
+}
+
+/// Names of the packages selected by `-p`, `--workspace` and `--exclude`,
+/// which accept glob patterns like `legacy_*`.
+///
+/// `--workspace` selects all the packages, minus the excluded ones, like
+/// other cargo commands. Patterns given to `-p` must match at least one
+/// package.
+pub fn select_packages(
+    targets: &[CrateTarget],
+    packages: &[String],
+    workspace: bool,
+    exclude: &[String],
+) -> Result<BTreeSet<String>, String> {
+    let patterns = |specs: &[String]| -> Result<Vec<glob::Pattern>, String> {
+        specs
+            .iter()
+            .map(|spec| {
+                glob::Pattern::new(spec).map_err(|e| format!("Invalid pattern '{}': {}", spec, e))
+            })
+            .collect()
+    };
+
+    let names: BTreeSet<_> = targets.iter().map(|t| t.package.as_str()).collect();
+
+    if workspace {
+        let exclude = patterns(exclude)?;
+        return Ok(names
+            .into_iter()
+            .filter(|name| !exclude.iter().any(|p| p.matches(name)))
+            .map(String::from)
+            .collect());
+    }
+
+    if !exclude.is_empty() {
+        return Err("`--exclude` can only be used together with `--workspace`".into());
+    }
+
+    let mut selected = BTreeSet::new();
+    for (spec, pattern) in packages.iter().zip(patterns(packages)?) {
+        let matching: Vec<_> = names.iter().filter(|name| pattern.matches(name)).collect();
+        if matching.is_empty() {
+            return Err(format!("Package '{}' not found in the workspace", spec));
+        }
+        selected.extend(matching.into_iter().map(|&name| String::from(name)));
+    }
+
+    Ok(selected)
+}
+
+/// Reads the `#![doc(html_root_url = "...")]` attribute from the root file of
+/// a crate.
+///
+/// The returned URL never ends with a `/`.
+pub fn html_root_url(root_file: &Path) -> Option<String> {
+    lazy_static::lazy_static! {
+        static ref HTML_ROOT_URL: Regex = Regex::new(
+            r#"#!\[\s*doc\s*\([^\]]*?html_root_url\s*=\s*"(?P<url>[^"]+)""#
+        ).unwrap();
+    }
+
+    let content = std::fs::read_to_string(root_file).ok()?;
+    let url = HTML_ROOT_URL.captures(&content)?.name("url")?.as_str();
+    Some(url.trim_end_matches('/').to_string())
+}
+
+/// Finds the most complete standard crate available to the crate `krate`
+/// whose root file is `root_file`.
+///
+/// Only an unconditional `#![no_std]` in the root file of the crate is
+/// detected, with `extern crate alloc;` making `alloc` available.
+pub fn facade(root_file: &Path, krate: &str) -> Facade {
+    lazy_static::lazy_static! {
+        static ref NO_STD: Regex = Regex::new(r"#!\[\s*no_std\s*\]").unwrap();
+        static ref EXTERN_ALLOC: Regex = Regex::new(r"extern\s+crate\s+alloc\s*;").unwrap();
+    }
+
+    // Working on the standard crates themselves.
+    if let Some(facade) = Facade::from_crate(krate) {
+        return facade;
+    }
 
-    // FIXME: This doesn't handle renamed packages for renames other than `-` -> `_`.
-    manifest.packages.into_iter().filter_map(|package| {
-        let path = package
-            .manifest_path
-            .parent()
-            .expect("A Cargo.toml cannot be the root")
-            .join("src");
-        let name = package.name.replace("-", "_");
+    let content = match std::fs::read_to_string(root_file) {
+        Ok(c) => c,
+        Err(_) => return Facade::Std,
+    };
 
-        if path.is_dir() {
-            Some((name, path))
+    if !NO_STD.is_match(&content) {
+        Facade::Std
+    } else if EXTERN_ALLOC.is_match(&content) {
+        Facade::Alloc
+    } else {
+        Facade::Core
+    }
+}
+
+/// Information about the dependencies of the workspace members, see
+/// `workspace_deps`.
+#[derive(Debug, Default)]
+pub struct WorkspaceDeps {
+    /// Documentation roots declared by the dependencies.
+    pub doc_roots: Vec<DocRoot>,
+
+    /// Dependencies visible when building the docs of each member, by
+    /// directory of the member's manifest.
+    members: HashMap<PathBuf, MemberDeps>,
+}
+
+/// Dependencies visible when building the docs of the targets of a workspace
+/// member.
+#[derive(Debug, Default)]
+struct MemberDeps {
+    /// For the library: the normal dependencies.
+    lib: Vec<Dependency>,
+
+    /// For the binaries: the library of the package too.
+    bin: Vec<Dependency>,
+
+    /// For tests, examples and benches: the dev-dependencies too.
+    dev: Vec<Dependency>,
+
+    /// For the build script: the build-dependencies only.
+    build: Vec<Dependency>,
+}
+
+impl WorkspaceDeps {
+    /// Dependencies of the library of the workspace member containing `path`,
+    /// if they are known.
+    pub fn for_path(&self, path: &Path) -> Option<&[Dependency]> {
+        self.member(path).map(|deps| deps.lib.as_slice())
+    }
+
+    /// Dependencies of `target`, if they are known.
+    pub fn for_target(&self, target: &CrateTarget) -> Option<&[Dependency]> {
+        let deps = self.member(&target.package_dir)?;
+        Some(if target.is_lib {
+            &deps.lib
+        } else if target.is_build_script() {
+            &deps.build
+        } else if target.is_dev {
+            &deps.dev
         } else {
-            None
+            &deps.bin
+        })
+    }
+
+    fn member(&self, path: &Path) -> Option<&MemberDeps> {
+        self.members
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, deps)| deps)
+    }
+}
+
+/// Finds the dependencies of the members of the current workspace, and the
+/// documentation roots they declare.
+///
+/// `cargo metadata` is run offline so only the dependencies already present
+/// in the local registry cache (after a build for example) are found. Errors
+/// are not reported: without this information, links to other crates are
+/// simply not favored.
+pub fn workspace_deps(manifest_path: Option<&Path>) -> WorkspaceDeps {
+    let offline = || vec!["--offline".to_string()];
+
+    // With all the features, the optional dependencies only enabled for the
+    // docs are resolved too. This fails when they are not available offline,
+    // the default features are used then.
+    let (manifest, all_features) = match metadata_command(manifest_path)
+        .features(CargoOpt::AllFeatures)
+        .other_options(offline())
+        .exec()
+    {
+        Ok(m) => (m, true),
+        Err(_) => match metadata_command(manifest_path)
+            .other_options(offline())
+            .exec()
+        {
+            Ok(m) => (m, false),
+            Err(_) => return WorkspaceDeps::default(),
+        },
+    };
+
+    let doc_roots = manifest
+        .packages
+        .iter()
+        .filter(|package| !manifest.workspace_members.contains(&package.id))
+        .filter_map(|package| {
+            let lib = package.targets.iter().find(|t| is_lib(t))?;
+            let url = html_root_url(&lib.src_path)?;
+
+            Some(DocRoot {
+                url,
+                krate: lib.name.replace("-", "_"),
+            })
+        })
+        .collect();
+
+    let nodes = manifest
+        .resolve
+        .as_ref()
+        .map_or(&[][..], |r| r.nodes.as_slice());
+
+    let members = manifest
+        .workspace_members
+        .iter()
+        .filter_map(|id| {
+            let package = &manifest[id];
+            let node = nodes.iter().find(|n| &n.id == id)?;
+            let root = package.manifest_path.parent()?.to_path_buf();
+
+            let deps = |kind| doc_dependencies(&manifest, package, node, all_features, kind);
+
+            // Binaries, tests, ... can use the library of their package.
+            let lib = package.targets.iter().find(|t| is_lib(t)).map(|t| {
+                let name = t.name.replace("-", "_");
+                Dependency {
+                    lib: name.clone(),
+                    name,
+                    version: Some(package.version.clone()),
+                }
+            });
+
+            let normal = deps(DependencyKind::Normal);
+            let bin: Vec<_> = normal.iter().cloned().chain(lib).collect();
+            let dev = bin
+                .iter()
+                .cloned()
+                .chain(deps(DependencyKind::Development))
+                .collect();
+            let member = MemberDeps {
+                lib: normal,
+                bin,
+                dev,
+                build: deps(DependencyKind::Build),
+            };
+
+            Some((root, member))
+        })
+        .collect();
+
+    WorkspaceDeps { doc_roots, members }
+}
+
+/// Dependencies of `package` of the given `kind` that are available when
+/// building its docs.
+///
+/// Those are the dependencies in the resolved graph `node`, minus the
+/// optional ones that are not enabled for the docs (see `docs_rs_deps`) when
+/// it was resolved with `all_features`. Otherwise it only contains the
+/// optional dependencies enabled by default and those enabled for the docs
+/// are added with their library name guessed from their package name.
+fn doc_dependencies(
+    manifest: &Metadata,
+    package: &Package,
+    node: &Node,
+    all_features: bool,
+    kind: DependencyKind,
+) -> Vec<Dependency> {
+    let enabled = docs_rs_deps(package);
+    let is_enabled = |name: &str| enabled.as_ref().is_none_or(|e| e.contains(name));
+
+    let mut deps: Vec<_> = node
+        .deps
+        .iter()
+        .filter(|dep| {
+            // Older versions of cargo do not give the kinds, all the
+            // dependencies are considered normal ones.
+            if dep.dep_kinds.is_empty() {
+                kind == DependencyKind::Normal
+            } else {
+                dep.dep_kinds.iter().any(|k| k.kind == kind)
+            }
+        })
+        .filter(|dep| {
+            let declared = package.dependencies.iter().filter(|d| {
+                d.kind == kind
+                    && d.name == manifest[&dep.pkg].name
+                    && d.rename
+                        .as_ref()
+                        .is_none_or(|r| r.replace("-", "_") == dep.name)
+            });
+
+            !all_features
+                || declared
+                    .into_iter()
+                    .any(|d| !d.optional || is_enabled(d.rename.as_deref().unwrap_or(&d.name)))
+        })
+        .map(|dep| {
+            let package = &manifest[&dep.pkg];
+            let lib = package
+                .targets
+                .iter()
+                .find(|t| is_lib(t))
+                .map_or_else(|| dep.name.clone(), |t| t.name.replace("-", "_"));
+
+            Dependency {
+                lib,
+                name: dep.name.clone(),
+                version: Some(package.version.clone()),
+            }
+        })
+        .collect();
+
+    if all_features {
+        return deps;
+    }
+
+    for dep in &package.dependencies {
+        if !dep.optional || dep.kind != kind {
+            continue;
+        }
+
+        let name = dep.rename.as_deref().unwrap_or(&dep.name);
+        let enabled = is_enabled(name);
+
+        let name = name.replace("-", "_");
+        if enabled && !deps.iter().any(|d| d.name == name) {
+            deps.push(Dependency {
+                lib: dep.name.replace("-", "_"),
+                name,
+                version: None,
+            });
         }
+    }
+
+    deps
+}
+
+/// Names of the optional dependencies of `package` enabled when building its
+/// docs, as in its manifest, or `None` when all of them are.
+///
+/// The features enabled by the `[package.metadata.docs.rs]` table (and the
+/// default ones, unless `no-default-features` is set) are followed through
+/// the `[features]` table of the package: `dep:name` and `name/feature`
+/// enable the dependency `name`, like a feature that is not in the table
+/// since optional dependencies are implicit features.
+fn docs_rs_deps(package: &Package) -> Option<BTreeSet<&str>> {
+    let docs_rs = package.metadata.get("docs").and_then(|d| d.get("rs"));
+    let flag = |key: &str| {
+        docs_rs
+            .and_then(|d| d.get(key))
+            .and_then(|a| a.as_bool())
+            .unwrap_or(false)
+    };
+
+    if flag("all-features") {
+        return None;
+    }
+
+    let mut features: Vec<_> = docs_rs
+        .and_then(|d| d.get("features"))
+        .and_then(|f| f.as_array())
+        .map(|f| f.iter().filter_map(|f| f.as_str()).collect())
+        .unwrap_or_default();
+    if !flag("no-default-features") {
+        features.push("default");
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut deps = BTreeSet::new();
+    while let Some(feature) = features.pop() {
+        if !seen.insert(feature) {
+            continue;
+        }
+
+        if let Some(dep) = feature.strip_prefix("dep:") {
+            deps.insert(dep);
+        } else if let Some((dep, _)) = feature.split_once('/') {
+            // `name?/feature` does not enable the dependency.
+            if !dep.ends_with('?') {
+                deps.insert(dep);
+            }
+        } else if let Some(enabled) = package.features.get(feature) {
+            features.extend(enabled.iter().map(String::as_str));
+        } else if feature != "default" {
+            deps.insert(feature);
+        }
+    }
+
+    Some(deps)
+}
+
+/// Checks if the target is a library, including procedural macros.
+fn is_lib(target: &Target) -> bool {
+    target.kind.iter().any(|k| {
+        matches!(
+            k.as_str(),
+            "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro"
+        )
     })
 }
+
+#[test]
+fn test_html_root_url() {
+    let fixture = crate::fixture(&[]);
+    let dir = fixture.path();
+
+    std::fs::write(dir.join("lib.rs"), "//! Docs.\n").unwrap();
+    assert_eq!(html_root_url(&dir.join("lib.rs")), None);
+
+    std::fs::write(
+        dir.join("lib.rs"),
+        "//! Docs.\n#![doc(html_root_url = \"https://docs.rs/regex/1.4.2/\")]\n",
+    )
+    .unwrap();
+    assert_eq!(
+        html_root_url(&dir.join("lib.rs")).as_deref(),
+        Some("https://docs.rs/regex/1.4.2")
+    );
+
+    std::fs::write(
+        dir.join("lib.rs"),
+        "#![doc(\n    html_logo_url = \"https://example.com/logo.png\",\n    html_root_url = \"https://docs.example/\"\n)]\n",
+    )
+    .unwrap();
+    assert_eq!(
+        html_root_url(&dir.join("lib.rs")).as_deref(),
+        Some("https://docs.example")
+    );
+}
+
+#[test]
+fn test_facade() {
+    let fixture = crate::fixture(&[]);
+    let dir = fixture.path();
+
+    assert_eq!(facade(&dir.join("lib.rs"), "krate"), Facade::Std);
+    assert_eq!(facade(&dir.join("lib.rs"), "core"), Facade::Core);
+    assert_eq!(facade(&dir.join("lib.rs"), "alloc"), Facade::Alloc);
+
+    std::fs::write(dir.join("lib.rs"), "//! Docs.\n").unwrap();
+    assert_eq!(facade(&dir.join("lib.rs"), "krate"), Facade::Std);
+
+    std::fs::write(dir.join("lib.rs"), "//! Docs.\n#![no_std]\n").unwrap();
+    assert_eq!(facade(&dir.join("lib.rs"), "krate"), Facade::Core);
+
+    std::fs::write(
+        dir.join("lib.rs"),
+        "//! Docs.\n#![no_std]\n\nextern crate alloc;\n",
+    )
+    .unwrap();
+    assert_eq!(facade(&dir.join("lib.rs"), "krate"), Facade::Alloc);
+
+    // Conditional `no_std` is not detected.
+    std::fs::write(
+        dir.join("lib.rs"),
+        "#![cfg_attr(not(feature = \"std\"), no_std)]\n",
+    )
+    .unwrap();
+    assert_eq!(facade(&dir.join("lib.rs"), "krate"), Facade::Std);
+}
+
+#[test]
+fn test_crate_targets() -> cargo_metadata::Result<()> {
+    let fixture = crate::fixture(&[
+        (
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"lib-pkg\", \"bin-pkg\"]\n",
+        ),
+        (
+            "lib-pkg/Cargo.toml",
+            "[package]\nname = \"lib-pkg\"\nversion = \"0.1.0\"\n\n[lib]\nname = \"renamed\"\n",
+        ),
+        ("lib-pkg/src/lib.rs", "//! Docs.\n"),
+        ("lib-pkg/src/mod1.rs", "//! Docs.\n"),
+        ("lib-pkg/src/notes.md", "Docs.\n"),
+        (
+            "bin-pkg/Cargo.toml",
+            "[package]\nname = \"bin-pkg\"\nversion = \"0.1.0\"\n",
+        ),
+        ("bin-pkg/src/main.rs", "//! Docs.\n"),
+        ("bin-pkg/src/cli.rs", "//! Docs.\n"),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let targets = crate_targets(Some(&dir.join("Cargo.toml")), &FileConfig::default(), false)?;
+
+    // The name of the library is used, not the one of the package.
+    let lib = targets.iter().find(|t| t.package == "lib-pkg").unwrap();
+    assert_eq!(lib.name, "renamed");
+    assert!(lib.is_lib);
+    assert_eq!(lib.root, dir.join("lib-pkg/src/lib.rs"));
+    assert_eq!(
+        lib.files,
+        [
+            dir.join("lib-pkg/src/lib.rs"),
+            dir.join("lib-pkg/src/mod1.rs")
+        ]
+    );
+
+    // Binary-only packages use the name of the binary.
+    let bin = targets.iter().find(|t| t.package == "bin-pkg").unwrap();
+    assert_eq!(bin.name, "bin_pkg");
+    assert!(!bin.is_lib);
+    assert_eq!(
+        bin.files,
+        [
+            dir.join("bin-pkg/src/cli.rs"),
+            dir.join("bin-pkg/src/main.rs")
+        ]
+    );
+
+    let owner = |file: &str| owning_target(&targets, &dir.join(file)).map(|i| &targets[i]);
+    assert_eq!(owner("lib-pkg/src/notes.md"), Some(lib));
+    assert_eq!(owner("bin-pkg/src/cli.rs"), Some(bin));
+    assert_eq!(owner("README.md"), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_target_kinds() -> cargo_metadata::Result<()> {
+    let fixture = crate::fixture(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["app", "helper"]
+"#,
+        ),
+        (
+            "app/Cargo.toml",
+            r#"[package]
+name = "app"
+version = "0.2.0"
+edition = "2018"
+
+[lib]
+path = "lib/app.rs"
+
+[dev-dependencies]
+helper = { path = "../helper" }
+"#,
+        ),
+        ("app/lib/app.rs", ""),
+        ("app/lib/util.rs", ""),
+        ("app/src/main.rs", ""),
+        ("app/src/bin/a.rs", ""),
+        ("app/src/bin/a/x.rs", ""),
+        ("app/src/bin/b.rs", ""),
+        ("app/src/bin/b/y.rs", ""),
+        ("app/src/bin/c/main.rs", ""),
+        ("app/src/bin/c/z.rs", ""),
+        ("app/examples/demo.rs", ""),
+        ("app/tests/it.rs", ""),
+        ("app/tests/common/mod.rs", ""),
+        (
+            "helper/Cargo.toml",
+            "[package]\nname = \"helper\"\nversion = \"0.1.0\"\n",
+        ),
+        ("helper/src/lib.rs", ""),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let manifest = dir.join("Cargo.toml");
+    let targets = crate_targets(Some(&manifest), &FileConfig::default(), false)?;
+
+    let target = |kind: &str, name: &str| {
+        targets
+            .iter()
+            .find(|t| t.package == "app" && t.name == name && t.root.starts_with(dir.join(kind)))
+            .unwrap()
+    };
+    let lib = target("app/lib", "app");
+    let main = target("app/src", "app");
+    let demo = target("app/examples", "demo");
+    let it = target("app/tests", "it");
+    assert!(lib.is_lib && !lib.is_dev);
+    assert!(!main.is_lib && !main.is_dev);
+    assert!(!demo.is_lib && demo.is_dev);
+    assert!(!it.is_lib && it.is_dev);
+
+    // Binaries sharing `src/bin` keep their own modules.
+    let owner = |file: &str| {
+        owning_target(&targets, &dir.join(file)).map(|i| (&*targets[i].name, targets[i].is_lib))
+    };
+    assert_eq!(owner("app/lib/util.rs"), Some(("app", true)));
+    assert_eq!(owner("app/src/bin/a/x.rs"), Some(("a", false)));
+    assert_eq!(owner("app/src/bin/b/y.rs"), Some(("b", false)));
+    assert_eq!(owner("app/src/bin/c/z.rs"), Some(("c", false)));
+    assert_eq!(owner("app/tests/common/mod.rs"), Some(("it", false)));
+
+    // Other targets can use the library, tests and examples the
+    // dev-dependencies too.
+    let deps = workspace_deps(Some(&manifest));
+    let names = |target: &CrateTarget| {
+        let mut names: Vec<_> = deps
+            .for_target(target)
+            .unwrap()
+            .iter()
+            .map(|d| d.lib.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    };
+    assert!(names(lib).is_empty());
+    assert_eq!(names(main), ["app"]);
+    assert_eq!(names(target("app/src/bin", "a")), ["app"]);
+    assert_eq!(names(demo), ["app", "helper"]);
+    assert_eq!(names(it), ["app", "helper"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_select_packages() {
+    let target = |package: &str| CrateTarget {
+        name: package.replace("-", "_"),
+        package: package.into(),
+        is_lib: true,
+        is_dev: false,
+        root: PathBuf::new(),
+        package_dir: PathBuf::new(),
+        files: Vec::new(),
+    };
+    let targets = [
+        target("app"),
+        target("legacy_a"),
+        target("legacy_b"),
+        target("core-utils"),
+        target("app"),
+    ];
+    let strings = |s: &[&str]| s.iter().map(|&s| String::from(s)).collect::<Vec<_>>();
+    let select = |packages: &[&str], workspace: bool, exclude: &[&str]| {
+        select_packages(&targets, &strings(packages), workspace, &strings(exclude))
+            .map(|s| s.into_iter().collect::<Vec<_>>())
+    };
+
+    assert_eq!(select(&[], false, &[]), Ok(vec![]));
+    assert_eq!(select(&["app"], false, &[]), Ok(strings(&["app"])));
+    assert_eq!(
+        select(&["app", "core-utils"], false, &[]),
+        Ok(strings(&["app", "core-utils"]))
+    );
+    assert_eq!(
+        select(&["legacy_*"], false, &[]),
+        Ok(strings(&["legacy_a", "legacy_b"]))
+    );
+    assert_eq!(
+        select(&[], true, &[]),
+        Ok(strings(&["app", "core-utils", "legacy_a", "legacy_b"]))
+    );
+    assert_eq!(
+        select(&[], true, &["legacy_*"]),
+        Ok(strings(&["app", "core-utils"]))
+    );
+    assert_eq!(
+        select(&["app"], true, &["app"]),
+        Ok(strings(&["core-utils", "legacy_a", "legacy_b"]))
+    );
+
+    assert!(select(&["missing"], false, &[]).is_err());
+    assert!(select(&["app"], false, &["legacy_*"]).is_err());
+    assert!(select(&["[app"], false, &[]).is_err());
+}
+
+#[test]
+fn test_mapped_targets() {
+    let fixture = crate::fixture(&[
+        ("libs/foo-bar/src/lib.rs", "//! Docs.\n"),
+        ("libs/foo-bar/src/mod1.rs", "//! Docs.\n"),
+        ("libs/foo-bar/src/mod1/mod2.rs", "//! Docs.\n"),
+        ("libs/baz/src/lib.rs", "//! Docs.\n"),
+        ("libs/no_root/src/mod1.rs", "//! Docs.\n"),
+        ("tools/app/main.rs", "//! Docs.\n"),
+        ("tools/app/cli.rs", "//! Docs.\n"),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+
+    // Relative patterns start from the directory of the configuration file.
+    let config: crate::RawFileConfig = toml::from_str(
+        "[[crates]]\ndir = 'libs/*'\n\n[[crates]]\ndir = 'tools/app'\nname = 'app'\nroot = 'main.rs'\n",
+    )
+    .unwrap();
+    let config = config.finish(&dir).unwrap();
+
+    let targets = mapped_targets(&config, false);
+    let names: Vec<_> = targets.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["baz", "foo_bar", "app"]);
+
+    let foo = &targets[1];
+    assert_eq!(foo.package, "foo-bar");
+    assert_eq!(foo.root, dir.join("libs/foo-bar/src/lib.rs"));
+    assert_eq!(
+        foo.files,
+        [
+            dir.join("libs/foo-bar/src/lib.rs"),
+            dir.join("libs/foo-bar/src/mod1/mod2.rs"),
+            dir.join("libs/foo-bar/src/mod1.rs"),
+        ]
+    );
+
+    let app = &targets[2];
+    assert_eq!(app.root, dir.join("tools/app/main.rs"));
+    assert_eq!(
+        app.files,
+        [dir.join("tools/app/cli.rs"), dir.join("tools/app/main.rs")]
+    );
+}
+
+#[test]
+fn test_skipped_files() {
+    let fixture = crate::fixture(&[
+        ("krate/src/lib.rs", "//! Docs.\n"),
+        ("krate/src/mod1.rs", "//! Docs.\n"),
+        ("krate/src/generated/gen.rs", "//! Docs.\n"),
+        ("krate/src/vendor/dep.rs", "//! Docs.\n"),
+        ("krate/src/legacy.rs", "//! Docs.\n"),
+        ("krate/src/notes.md", "Docs.\n"),
+        ("krate/.gitignore", "generated/\n"),
+        ("krate/src/.ignore", "vendor\n"),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+    // Symbolic links loops are skipped.
+    #[cfg(unix)]
+    {
+        std::fs::create_dir_all(dir.join("krate/src/mod1")).unwrap();
+        std::os::unix::fs::symlink(dir.join("krate/src"), dir.join("krate/src/mod1/loop")).unwrap();
+    }
+
+    let pattern = |p: &str| format!("{}/{}", glob::Pattern::escape(&dir.to_string_lossy()), p);
+    let config: crate::RawFileConfig = toml::from_str(&format!(
+        "exclude = ['{}']\n\n[[crates]]\ndir = '{}'\n",
+        pattern("**/legacy.rs"),
+        pattern("krate"),
+    ))
+    .unwrap();
+
+    let targets = mapped_targets(&config.finish(Path::new(".")).unwrap(), false);
+    assert_eq!(
+        targets[0].files,
+        [dir.join("krate/src/lib.rs"), dir.join("krate/src/mod1.rs")]
+    );
+
+    let config: crate::RawFileConfig = toml::from_str(&format!(
+        "include = ['{}']\n\n[[crates]]\ndir = '{}'\n",
+        pattern("**/mod1.rs"),
+        pattern("krate"),
+    ))
+    .unwrap();
+
+    let targets = mapped_targets(&config.finish(Path::new(".")).unwrap(), false);
+    assert_eq!(targets[0].files, [dir.join("krate/src/mod1.rs")]);
+
+    // Skipped entries are found in the same walk, without the files of the
+    // target directory.
+    std::fs::create_dir_all(dir.join("krate/target")).unwrap();
+    std::fs::write(dir.join("krate/target/out.rs"), "").unwrap();
+    let mut skipped = BTreeSet::new();
+    let files = rust_files(
+        &dir.join("krate"),
+        &dir.join("krate/target"),
+        true,
+        &mut skipped,
+    );
+    assert_eq!(files.len(), 3);
+    let ignored = "ignored by a `.gitignore` or `.ignore` file";
+    let mut expected = vec![
+        (dir.join("krate/src/generated"), ignored),
+        (dir.join("krate/src/vendor"), ignored),
+    ];
+    #[cfg(unix)]
+    expected.push((dir.join("krate/src/mod1/loop"), ""));
+    expected.sort();
+    let skipped: Vec<_> = skipped
+        .iter()
+        .map(|(p, r)| (p.clone(), if r == ignored { ignored } else { "" }))
+        .collect();
+    assert_eq!(skipped, expected);
+}
+
+#[test]
+fn test_parse_file_list() {
+    let paths = |s: &[&str]| s.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+    assert_eq!(parse_file_list(""), paths(&[]));
+    assert_eq!(
+        parse_file_list("src/lib.rs\nsrc/mod 1.rs\n"),
+        paths(&["src/lib.rs", "src/mod 1.rs"])
+    );
+    assert_eq!(
+        parse_file_list("src/lib.rs\r\n\nREADME.md"),
+        paths(&["src/lib.rs", "README.md"])
+    );
+    assert_eq!(
+        parse_file_list("src/lib.rs\0src/with\nnewline.rs\0"),
+        paths(&["src/lib.rs", "src/with\nnewline.rs"])
+    );
+}
+
+#[test]
+fn test_workspace_deps() {
+    let package = |name: &str, extra: &str| {
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n{}",
+            name, extra
+        )
+    };
+    let a = package(
+        "a",
+        r#"
+[dependencies]
+bee = { package = "b", path = "../b" }
+c = { path = "../c", optional = true }
+d = { path = "../d", optional = true }
+e = { path = "../e", optional = true }
+
+[features]
+default = ["e"]
+docs = ["dep:c"]
+
+[package.metadata.docs.rs]
+features = ["docs"]
+"#,
+    );
+    let c = package("c", "\n[lib]\nname = \"c_lib\"\n");
+    let fixture = crate::fixture(&[
+        (
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\", \"d\", \"e\"]\n",
+        ),
+        ("a/Cargo.toml", &a),
+        ("a/src/lib.rs", ""),
+        ("b/Cargo.toml", &package("b", "")),
+        ("b/src/lib.rs", ""),
+        ("c/Cargo.toml", &c),
+        ("c/src/lib.rs", ""),
+        ("d/Cargo.toml", &package("d", "")),
+        ("d/src/lib.rs", ""),
+        ("e/Cargo.toml", &package("e", "")),
+        ("e/src/lib.rs", ""),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+
+    let deps = workspace_deps(Some(&dir.join("Cargo.toml")));
+    let mut a_deps: Vec<_> = deps
+        .for_path(&dir.join("a/src/lib.rs"))
+        .unwrap()
+        .iter()
+        .map(|d| (d.lib.as_str(), d.name.as_str()))
+        .collect();
+    a_deps.sort_unstable();
+    assert_eq!(a_deps, [("b", "bee"), ("c_lib", "c_lib"), ("e", "e")]);
+    assert_eq!(deps.for_path(&dir.join("b/src/lib.rs")), Some(&[][..]));
+}
@@ -0,0 +1,394 @@
+//! Finds the files and lines changed in a git repository, by running the
+//! `git` binary.
+//!
+//! This is used to convert a codebase incrementally: only the files (and
+//! optionally the lines) touched since a revision, or staged for the next
+//! commit, are converted.
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Changes to consider.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Changes<'a> {
+    /// Changes between a revision and the working tree, with the untracked
+    /// files that are not ignored.
+    Since(&'a str),
+    /// Changes staged in the index, compared to `HEAD`.
+    Staged,
+}
+
+/// A git repository.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Repo {
+    /// Root of the working tree, where git is run.
+    toplevel: PathBuf,
+}
+
+impl Repo {
+    /// Finds the repository containing `dir`.
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let toplevel = git(dir, &["rev-parse", "--show-toplevel"])?;
+        let toplevel = String::from_utf8_lossy(&toplevel);
+
+        Ok(Self {
+            toplevel: PathBuf::from(toplevel.trim_end_matches('\n')),
+        })
+    }
+
+    /// Absolute paths of the `.rs` and `.md` files added, copied, modified or
+    /// renamed by `changes`.
+    pub fn changed_files(&self, changes: Changes) -> Result<Vec<PathBuf>, String> {
+        let mut args = vec![
+            "diff",
+            "--no-relative",
+            "--name-only",
+            "-z",
+            "--diff-filter=ACMR",
+        ];
+        args.extend(changes.diff_args());
+        let mut output = self.git(&args)?;
+        output.extend(self.untracked_files(changes)?);
+
+        Ok(String::from_utf8_lossy(&output)
+            .split('\0')
+            .map(Path::new)
+            .filter(|p| p.extension().is_some_and(|ext| ext == "rs" || ext == "md"))
+            .map(|p| self.toplevel.join(p))
+            .collect())
+    }
+
+    /// Untracked files that are not ignored for `Changes::Since`, separated
+    /// by NUL characters and relative to the root of the working tree.
+    fn untracked_files(&self, changes: Changes) -> Result<Vec<u8>, String> {
+        match changes {
+            Changes::Since(_) => self.git(&["ls-files", "--others", "--exclude-standard", "-z"]),
+            Changes::Staged => Ok(Vec::new()),
+        }
+    }
+
+    /// Lines added or modified by `changes` in each file, by absolute path.
+    ///
+    /// The lines are numbered from one and are those of the new version of
+    /// the files: the working tree for `Changes::Since` and the index for
+    /// `Changes::Staged`.
+    pub fn changed_lines(&self, changes: Changes) -> Result<ChangedLines, String> {
+        let mut args = vec![
+            "diff",
+            "--no-relative",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+        ];
+        args.extend(changes.diff_args());
+        let output = self.git(&args)?;
+        let mut changed = parse_changed_lines(&String::from_utf8_lossy(&output));
+
+        // All the lines of untracked files are new.
+        let untracked = self.untracked_files(changes)?;
+        for path in String::from_utf8_lossy(&untracked).split('\0') {
+            if !path.is_empty() {
+                changed.insert(PathBuf::from(path), vec![1..=usize::MAX]);
+            }
+        }
+
+        Ok(changed
+            .into_iter()
+            .map(|(path, lines)| (self.toplevel.join(path), lines))
+            .collect())
+    }
+
+    /// Content of the file at `path` (absolute) in the index.
+    pub fn staged_content(&self, path: &Path) -> Result<Vec<u8>, String> {
+        let path = self.relative(path)?;
+        self.git(&["show", &format!(":{}", path.display())])
+    }
+
+    /// Stages the content of the files at `paths` (absolute), with a single
+    /// `git add` for each batch of files.
+    pub fn stage(&self, paths: &[PathBuf]) -> Result<(), String> {
+        let paths = paths
+            .iter()
+            .map(|p| self.relative(p).map(|p| p.to_string_lossy()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Batches keep the command line short enough. File names are not
+        // patterns, even when they contain `*` or `?`.
+        for batch in paths.chunks(500) {
+            let mut args = vec!["--literal-pathspecs", "add", "--"];
+            args.extend(batch.iter().map(|p| p.as_ref()));
+            self.git(&args)?;
+        }
+        Ok(())
+    }
+
+    /// Checks if the file at `path` (absolute) has changes that are not
+    /// staged.
+    pub fn has_unstaged_changes(&self, path: &Path) -> Result<bool, String> {
+        let path = self.relative(path)?;
+        let status = Command::new("git")
+            .args(["diff", "--quiet", "--"])
+            .arg(path)
+            .current_dir(&self.toplevel)
+            .status()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+
+        match status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(format!("git diff failed ({})", status)),
+        }
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> Result<&'a Path, String> {
+        path.strip_prefix(&self.toplevel)
+            .map_err(|_| format!("'{}' is not in the repository", path.display()))
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Vec<u8>, String> {
+        git(&self.toplevel, args)
+    }
+}
+
+/// Lines changed in each file, see `Repo::changed_lines`.
+pub type ChangedLines = HashMap<PathBuf, Vec<RangeInclusive<usize>>>;
+
+impl<'a> Changes<'a> {
+    /// Arguments selecting the changes for `git diff`, ending the options so
+    /// a revision starting with `-` is not read as one.
+    fn diff_args(self) -> Vec<&'a str> {
+        match self {
+            Changes::Since(rev) => vec!["--end-of-options", rev, "--"],
+            Changes::Staged => vec!["--cached", "--"],
+        }
+    }
+}
+
+/// Runs git with `args` in `dir`, returning its output or its error.
+///
+/// Paths are not quoted in its output unless they contain control characters,
+/// quotes or backslashes.
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Reads the lines added in each file of a diff without context lines, by
+/// path relative to the root of the repository.
+fn parse_changed_lines(diff: &str) -> ChangedLines {
+    let mut changed = HashMap::new();
+    let mut current = None;
+    let mut previous = "";
+
+    for line in diff.lines() {
+        let prev = std::mem::replace(&mut previous, line);
+
+        // An added line can start with `++ `, the header always follows the
+        // `--- ` line.
+        if let Some(path) = line
+            .strip_prefix("+++ ")
+            .filter(|_| prev.starts_with("--- "))
+        {
+            // Deleted files have no new lines. Paths with spaces are followed
+            // by a tab.
+            current = unquote(path.trim_end_matches('\t'))
+                .strip_prefix("b/")
+                .map(PathBuf::from);
+            continue;
+        }
+
+        let path = match &current {
+            Some(path) => path,
+            None => continue,
+        };
+
+        // `@@ -start[,count] +start[,count] @@ ...`
+        let new = match line
+            .strip_prefix("@@ ")
+            .and_then(|h| h.split(' ').nth(1))
+            .and_then(|n| n.strip_prefix('+'))
+        {
+            Some(new) => new,
+            None => continue,
+        };
+
+        let mut parts = new.splitn(2, ',');
+        let start = parts.next().and_then(|s| s.parse::<usize>().ok());
+        let count = parts.next().map_or(Some(1), |c| c.parse::<usize>().ok());
+
+        if let (Some(start), Some(count)) = (start, count) {
+            // Hunks removing lines only have no new lines.
+            if count > 0 {
+                changed
+                    .entry(path.clone())
+                    .or_insert_with(Vec::new)
+                    .push(start..=start + count - 1);
+            }
+        }
+    }
+
+    changed
+}
+
+/// Reads a path quoted by git, like `"b/tab\there"`. Others are returned as
+/// is.
+fn unquote(path: &str) -> String {
+    let quoted = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(quoted) => quoted,
+        None => return path.to_string(),
+    };
+
+    // Escaped bytes like `\303\251` are parts of UTF-8 characters.
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        let byte = match chars.next() {
+            Some('a') => 0x07,
+            Some('b') => 0x08,
+            Some('f') => 0x0c,
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('v') => 0x0b,
+            Some(d @ '0'..='7') => {
+                let octal: String = std::iter::once(d).chain(chars.by_ref().take(2)).collect();
+                u8::from_str_radix(&octal, 8).unwrap_or(b'?')
+            }
+            Some(other) => other as u8,
+            None => b'\\',
+        };
+        bytes.push(byte);
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[test]
+fn test_parse_changed_lines() {
+    let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1234567..89abcde 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3 @@ fn main() {
+-/// [old](struct.Old.html)
++/// [new](struct.New.html)
+@@ -5 +5 @@
+-/// Old.
++++ b/not/a/header.rs
+@@ -10,0 +11,2 @@
++/// Added.
++/// Added.
+@@ -20,2 +21,0 @@
+-/// Removed.
+-/// Removed.
+diff --git a/old.md b/old.md
+deleted file mode 100644
+--- a/old.md
++++ /dev/null
+@@ -1 +0,0 @@
+-Removed.
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1,3 +1,4 @@
++# Title
+";
+
+    let changed = parse_changed_lines(diff);
+    assert_eq!(changed.len(), 2);
+    assert_eq!(changed[Path::new("src/lib.rs")], [3..=3, 5..=5, 11..=12]);
+    assert_eq!(changed[Path::new("README.md")], [1..=4]);
+}
+
+#[test]
+fn test_unquote() {
+    assert_eq!(unquote("b/src/lib.rs"), "b/src/lib.rs");
+    assert_eq!(unquote(r#""b/caf\303\251.md""#), "b/café.md");
+    assert_eq!(unquote(r#""b/tab\there""#), "b/tab\there");
+    assert_eq!(unquote(r#""b/\"quoted\" \\ back""#), "b/\"quoted\" \\ back");
+
+    let diff = "\
+--- \"a/caf\\303\\251.md\"
++++ \"b/caf\\303\\251.md\"
+@@ -1 +1 @@
+--- a/with space.md\t
++++ b/with space.md\t
+@@ -2 +2 @@
+";
+    let changed = parse_changed_lines(diff);
+    assert_eq!(changed[Path::new("café.md")], [1..=1]);
+    assert_eq!(changed[Path::new("with space.md")], [2..=2]);
+}
+
+#[test]
+fn test_repo() {
+    let fixture = crate::git_fixture(&[
+        ("src/lib.rs", "//! Committed.\n"),
+        ("src/café.rs", "//! Committed.\n"),
+        (".gitignore", "ignored.rs\n"),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let repo = Repo::open(&dir.join("src")).unwrap();
+    let lib = dir.join("src/lib.rs");
+    let cafe = dir.join("src/café.rs");
+
+    // Staged content and unstaged changes.
+    std::fs::write(&lib, "//! Staged.\n").unwrap();
+    crate::run_git(&dir, &["add", "src/lib.rs"]);
+    assert!(!repo.has_unstaged_changes(&lib).unwrap());
+    std::fs::write(&lib, "//! Staged.\n//! Unstaged.\n").unwrap();
+    assert!(repo.has_unstaged_changes(&lib).unwrap());
+    assert_eq!(repo.staged_content(&lib).unwrap(), b"//! Staged.\n");
+    assert!(repo.staged_content(&dir.join("src/missing.rs")).is_err());
+
+    // Untracked files are changes since a revision, not staged ones.
+    std::fs::write(&cafe, "//! Changed.\n//! Changed.\n").unwrap();
+    std::fs::write(dir.join("src/new.rs"), "//! New.\n").unwrap();
+    std::fs::write(dir.join("src/ignored.rs"), "//! Ignored.\n").unwrap();
+
+    let mut files = repo.changed_files(Changes::Since("HEAD")).unwrap();
+    files.sort();
+    assert_eq!(
+        files,
+        [cafe.clone(), dir.join("src/lib.rs"), dir.join("src/new.rs")]
+    );
+    assert_eq!(
+        repo.changed_files(Changes::Staged).unwrap(),
+        [lib.as_path()]
+    );
+
+    let lines = repo.changed_lines(Changes::Since("HEAD")).unwrap();
+    assert_eq!(lines[&cafe], [1..=2]);
+    assert_eq!(lines[&lib], [1..=2]);
+    assert!(lines[&dir.join("src/new.rs")][0].contains(&1000));
+
+    // Revisions are never read as options.
+    let output = dir.join("output");
+    let option = format!("--output={}", output.display());
+    assert!(repo.changed_files(Changes::Since(&option)).is_err());
+    assert!(!output.exists());
+
+    // Staging updates the index.
+    repo.stage(std::slice::from_ref(&lib)).unwrap();
+    assert!(!repo.has_unstaged_changes(&lib).unwrap());
+}
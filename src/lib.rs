@@ -5,24 +5,36 @@ mod config_file;
 mod consts;
 #[macro_use]
 mod error;
+mod facade;
 mod file_finder;
+mod git;
 mod link_parts;
 mod options;
+mod sections;
 mod transform;
 
+#[cfg(test)]
+mod tests;
+
 use action::Action;
 use candidate::Candidate;
 use cli_args::CliArgs;
-use config_file::{FileConfig, RawFileConfig};
+use config_file::{
+    FavoredHost, FileConfig, HostSegment, ImplAnchors, RawFileConfig, VersionMismatch,
+};
 use consts::*;
-use options::{ConversionOptions, Krate};
+use facade::Facade;
+use file_finder::{CrateTarget, WorkspaceDeps};
+use options::{ConversionOptions, Dependency, DocRoot, Krate};
+use rayon::prelude::*;
 use transform::ConversionContext;
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
 use std::fmt::Write;
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Write as _};
-use std::path::Path;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Write as _};
+use std::path::{Path, PathBuf};
 
 /// Takes an `CliArgs` instance to transform the paths it contains accordingly
 /// with its stored parameters.
@@ -38,21 +50,32 @@ pub fn run(mut args: CliArgs) {
 
     let start_dir = code_error!(1, env::current_dir(), "Failed to get current directory");
 
-    let file_config = if let Some(conf_file) = &args.config_file {
+    let manifest_path = args.manifest_path.as_deref();
+    // Directory of the manifest given with `--manifest-path` or the current
+    // one.
+    let manifest_dir = manifest_path
+        .and_then(Path::parent)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    // Paths in the configuration file given with `-i` are relative to the
+    // current directory, those in the default one to its directory.
+    let (file_config, config_dir) = if let Some(conf_file) = &args.config_file {
         let conf_file = code_error!(
             1,
             std::fs::read(conf_file),
             "Failed to read the given configuration file"
         );
-        Some(conf_file)
-    } else if let Ok(conf_file) = std::fs::read("intraconv.toml") {
+        (Some(conf_file), Path::new("."))
+    } else {
         // Errors are silently ignored when **opening and reading** the default
         // configuration file. THIS IS VOLUNTARY so that `cargo-intraconv` does
         // not produce an error when it is not present because the user does
         // not want/need one.
-        Some(conf_file)
-    } else {
-        None
+        (
+            std::fs::read(manifest_dir.join("intraconv.toml")).ok(),
+            manifest_dir,
+        )
     };
 
     let file_config: FileConfig = if let Some(cf) = file_config {
@@ -68,12 +91,10 @@ pub fn run(mut args: CliArgs) {
         );
         code_error!(
             1,
-            fc.finish(),
+            fc.finish(config_dir),
             "Failed to canonicalize a path from the configuration file, \
-            check it is correct when starting from the directory on which \
-            you called `{}` ({})",
-            std::env!("CARGO_PKG_NAME"),
-            start_dir.display(),
+            check it is correct when starting from '{}'",
+            start_dir.join(config_dir).display(),
         )
     } else {
         Default::default()
@@ -88,148 +109,696 @@ pub fn run(mut args: CliArgs) {
     .name()
     .to_string();
 
-    if args.paths.is_empty() || args.paths == [Path::new("intraconv")] {
-        args.paths.push(Path::new(".").into());
-    }
-
-    let mut paths = args.paths.iter();
+    let doc_root = if args.check_sections {
+        let target_dir = file_finder::metadata_command(manifest_path)
+            .no_deps()
+            .exec()
+            .map(|m| m.target_directory)
+            .unwrap_or_else(|_| start_dir.join("target"));
+        Some(target_dir.join("doc"))
+    } else {
+        None
+    };
 
+    // When called as `cargo intraconv`, the first argument is the name of the
+    // subcommand.
     if args
         .paths
-        .get(0)
-        .map_or(false, |p| p.as_os_str() == "intraconv")
+        .first()
+        .is_some_and(|p| p.as_os_str() == "intraconv")
     {
-        paths.next();
-    }
-
-    for path in paths {
-        if path.is_dir() {
-            let path = continue_error!(path.canonicalize(), "Failed to canonicalize '{:?}'", path);
-
-            for (maybe_crate_id, src_dir) in file_finder::crate_and_src() {
-                if !src_dir
-                    .parent()
-                    .expect("A source dir will always have a parent")
-                    .starts_with(&path)
-                {
-                    // file_finder::crate_and_src will find every crate in the
-                    // current workspace regardless of the current directory.
-                    // To prevent this we skip the files that are outside of
-                    // the currently considered directory.
-                    continue;
-                }
+        args.paths.remove(0);
+    }
 
-                match Krate::new(&maybe_crate_id) {
-                    Some(_) => args.krate = maybe_crate_id,
-                    None => {
-                        eprintln!(
-                            "Invalid crate identifier '{}', using the default '{}'",
-                            maybe_crate_id, &default_crate
-                        );
-                        args.krate = default_crate.clone();
-                    }
-                }
+    let selects_packages = args.workspace || !args.packages.is_empty() || !args.exclude.is_empty();
 
-                code_error!(
-                    1,
-                    env::set_current_dir(&src_dir),
-                    "Failed to set current directory to '{:?}'",
-                    src_dir
-                );
-
-                for file in glob::glob("**/*.rs").unwrap() {
-                    run_for_file(
-                        &continue_error!(&file, "Failed to access '{:?}' in '{:?}'", &file, &path),
-                        &args,
-                        &file_config,
-                    );
+    let changes = match (&args.changed_since, args.staged) {
+        (Some(_), true) => {
+            eprintln!("`--changed-since` and `--staged` cannot be used together");
+            std::process::exit(1);
+        }
+        (Some(rev), false) => Some(git::Changes::Since(rev)),
+        (None, true) => Some(git::Changes::Staged),
+        (None, false) => None,
+    };
+    if args.only_changed_lines && changes.is_none() {
+        eprintln!("`--only-changed-lines` needs `--changed-since` or `--staged`");
+        std::process::exit(1);
+    }
+    // Even when no file changed, the standard input is not one of them.
+    if args.stdin && changes.is_some() {
+        eprintln!("`--stdin` cannot be used with `--changed-since` or `--staged`");
+        std::process::exit(1);
+    }
+    if args.stdin && (args.apply || args.out_dir.is_some()) {
+        eprintln!("`--stdin` never writes on disk, it cannot be used with `-a` or `--out-dir`");
+        std::process::exit(1);
+    }
+    let repo = changes.map(|_| {
+        code_error!(
+            1,
+            git::Repo::open(&start_dir),
+            "Failed to find the git repository"
+        )
+    });
+
+    let mut listed_files = match &args.files_from {
+        Some(source) => code_error!(
+            1,
+            file_finder::read_file_list(source),
+            "Failed to read the list of files from '{}'",
+            source.display()
+        ),
+        None => Vec::new(),
+    };
+
+    let mut changed_lines = None;
+    if let (Some(changes), Some(repo)) = (changes, &repo) {
+        let current_dir = start_dir
+            .canonicalize()
+            .unwrap_or_else(|_| start_dir.clone());
+        listed_files.extend(
+            code_error!(
+                1,
+                repo.changed_files(changes),
+                "Failed to list the changed files"
+            )
+            .into_iter()
+            .filter(|f| f.starts_with(&current_dir)),
+        );
+
+        if args.only_changed_lines {
+            changed_lines = Some(code_error!(
+                1,
+                repo.changed_lines(changes),
+                "Failed to find the changed lines"
+            ));
+        }
+    }
+
+    if args.stdin_filename.is_some() && !args.stdin {
+        eprintln!("`--stdin-filename` can only be used together with `--stdin`");
+        std::process::exit(1);
+    }
+    if args.stdin && (!args.paths.is_empty() || !listed_files.is_empty() || selects_packages) {
+        eprintln!("`--stdin` cannot be used with other files to convert");
+        std::process::exit(1);
+    }
+
+    if args.paths.is_empty()
+        && !selects_packages
+        && args.files_from.is_none()
+        && changes.is_none()
+        && !args.stdin
+    {
+        args.paths.push(manifest_dir.into());
+    }
+
+    // Files are mirrored from the directory of the manifest given with
+    // `--manifest-path` or from the current directory.
+    let out_dir = args.out_dir.as_ref().map(|out_dir| {
+        let base_dir = manifest_dir;
+        code_error!(
+            1,
+            std::fs::create_dir_all(out_dir),
+            "Failed to create the output directory '{}'",
+            out_dir.display()
+        );
+
+        let out_dir = code_error!(
+            1,
+            out_dir.canonicalize(),
+            "Failed to canonicalize '{:?}'",
+            out_dir
+        );
+        let base_dir = code_error!(
+            1,
+            base_dir.canonicalize(),
+            "Failed to canonicalize '{:?}'",
+            base_dir
+        );
+
+        // The mirrored files would be written over the sources, or in a
+        // directory that is itself mirrored.
+        if base_dir.starts_with(&out_dir) {
+            eprintln!(
+                "The output directory '{}' cannot contain the mirrored directory '{}'",
+                out_dir.display(),
+                base_dir.display()
+            );
+            std::process::exit(1);
+        }
+
+        (out_dir, base_dir)
+    });
+
+    let targets = if selects_packages || args.paths.iter().any(|p| p.is_dir()) {
+        code_error!(
+            1,
+            file_finder::crate_targets(manifest_path, &file_config, args.verbose),
+            "This is not a cargo directory, pass the files explicitly, use \
+            `--manifest-path` or declare the crates in `intraconv.toml`"
+        )
+    } else if !listed_files.is_empty() || args.stdin {
+        // Listed files can be converted without knowing their crate.
+        file_finder::crate_targets(manifest_path, &file_config, args.verbose).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let workspace_crates = workspace_crates(&targets);
+
+    let stdin_filename = args
+        .stdin_filename
+        .as_deref()
+        .map(|f| f.canonicalize().unwrap_or_else(|_| start_dir.join(f)));
+    let stdin_target = stdin_filename
+        .as_deref()
+        .and_then(|f| file_finder::owning_target(&targets, f));
+
+    // Files to convert by canonical path, to give them a stable order and to
+    // never convert a file twice.
+    let mut files = BTreeMap::new();
+
+    if selects_packages {
+        let selected = code_error!(
+            1,
+            file_finder::select_packages(&targets, &args.packages, args.workspace, &args.exclude),
+            "Invalid package selection"
+        );
+
+        for (idx, target) in targets.iter().enumerate() {
+            if selected.contains(&target.package) {
+                for file in &target.files {
+                    files
+                        .entry(file.clone())
+                        .or_insert((file.as_path(), Some(idx)));
                 }
+            }
+        }
+    }
+
+    for path in &args.paths {
+        let canonical = continue_error!(path.canonicalize(), "Failed to canonicalize '{:?}'", path);
+
+        if !path.is_dir() {
+            files.entry(canonical).or_insert((path.as_path(), None));
+            continue;
+        }
 
-                code_error!(
-                    1,
-                    env::set_current_dir(&start_dir),
-                    "Failed to set current directory back to '{:?}'",
-                    start_dir
-                );
+        // file_finder::crate_targets will find every crate in the current
+        // workspace regardless of the current directory. To prevent this we
+        // skip the files that are outside of the currently considered
+        // directory.
+        for (idx, target) in targets.iter().enumerate() {
+            for file in target.files.iter().filter(|f| f.starts_with(&canonical)) {
+                files
+                    .entry(file.clone())
+                    .or_insert((file.as_path(), Some(idx)));
             }
-        } else {
-            if args.krate != default_crate {
-                args.krate = default_crate.clone();
+        }
+    }
+
+    // Listed files are often computed by other tools, their crate is found
+    // from their location.
+    for path in &listed_files {
+        let canonical = continue_error!(path.canonicalize(), "Failed to canonicalize '{:?}'", path);
+        let target = file_finder::owning_target(&targets, &canonical);
+        files.entry(canonical).or_insert((path.as_path(), target));
+    }
+
+    // Files written by a previous run must not be converted again.
+    if let Some((out_dir, _)) = &out_dir {
+        files.retain(|canonical, _| !canonical.starts_with(out_dir));
+    }
+
+    let used_targets: BTreeSet<_> = files
+        .values()
+        .filter_map(|&(_, idx)| idx)
+        .chain(stdin_target)
+        .collect();
+
+    // Without favored links the dependencies would never be used, and they
+    // are only needed for the files of a package: there is no need to run
+    // `cargo metadata` on all of them otherwise.
+    let deps = if args.no_favored || used_targets.is_empty() {
+        Default::default()
+    } else {
+        file_finder::workspace_deps(manifest_path)
+    };
+
+    let run_config = RunConfig {
+        args: &args,
+        file_config: &file_config,
+        doc_root: doc_root.as_deref(),
+        deps: &deps,
+        default_crate: &default_crate,
+        start_dir: &start_dir,
+        repo: repo.as_ref(),
+        changed_lines: changed_lines.as_ref(),
+        out_dir: out_dir
+            .as_ref()
+            .map(|(out, base)| (out.as_path(), base.as_path())),
+    };
+
+    if args.stdin {
+        let crate_info = match stdin_target {
+            Some(idx) => target_crate_info(&run_config, &targets[idx], &workspace_crates),
+            None => CrateInfo {
+                krate: default_crate.clone(),
+                ..Default::default()
+            },
+        };
+
+        code_error!(
+            1,
+            run_for_stdin(stdin_filename.as_deref(), &run_config, &crate_info),
+            "Failed to convert the standard input"
+        );
+        return;
+    }
+
+    let crate_infos: HashMap<_, _> = used_targets
+        .into_iter()
+        .map(|idx| {
+            let info = target_crate_info(&run_config, &targets[idx], &workspace_crates);
+            (idx, info)
+        })
+        .collect();
+
+    // Printing once everything is done keeps the output of each file together
+    // and in the same order between runs.
+    for report in run_for_files(files, &run_config, &crate_infos) {
+        match report {
+            Ok(output) => print!("{}", output),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+}
+
+/// Everything that does not change between the files of a run.
+struct RunConfig<'a> {
+    args: &'a CliArgs,
+    file_config: &'a FileConfig,
+    doc_root: Option<&'a Path>,
+    deps: &'a WorkspaceDeps,
+    default_crate: &'a str,
+    start_dir: &'a Path,
+    /// Set with `--changed-since` and `--staged`.
+    repo: Option<&'a git::Repo>,
+    /// Set with `--only-changed-lines`.
+    changed_lines: Option<&'a git::ChangedLines>,
+    /// Set with `--out-dir`: the output directory and the directory whose
+    /// layout is mirrored in it, both canonicalized.
+    out_dir: Option<(&'a Path, &'a Path)>,
+}
+
+/// Names of the library crates of the workspace.
+fn workspace_crates(targets: &[CrateTarget]) -> Vec<String> {
+    targets
+        .iter()
+        .filter(|t| t.is_lib)
+        .map(|t| t.name.clone())
+        .collect()
+}
+
+/// Information about the crate of `target`.
+fn target_crate_info<'a>(
+    run_config: &RunConfig<'a>,
+    target: &'a CrateTarget,
+    workspace_crates: &'a [String],
+) -> CrateInfo<'a> {
+    let krate = match Krate::new(&target.name) {
+        Some(_) => target.name.clone(),
+        None => {
+            eprintln!(
+                "Invalid crate identifier '{}', using the default '{}'",
+                target.name, run_config.default_crate
+            );
+            run_config.default_crate.to_string()
+        }
+    };
+
+    CrateInfo {
+        facade: file_finder::facade(&target.root, &krate),
+        krate,
+        html_root_url: file_finder::html_root_url(&target.root),
+        dependencies: run_config.deps.for_target(target),
+        workspace_crates,
+        root: Some(&target.root),
+        root_dir: Some(target.root_dir()),
+    }
+}
+
+/// Information about the crate a file is part of.
+///
+/// Parts of it are only known when working on directories, where the root of
+/// the crate is known.
+#[derive(Debug, Default)]
+struct CrateInfo<'a> {
+    /// Name of the crate, used for `crate::` paths.
+    krate: String,
+
+    /// See `ConversionOptions::html_root_url`.
+    html_root_url: Option<String>,
+
+    /// See `ConversionOptions::dependencies`.
+    dependencies: Option<&'a [Dependency]>,
+
+    /// See `ConversionOptions::facade`.
+    facade: Facade,
+
+    /// See `ConversionOptions::workspace_crates`.
+    workspace_crates: &'a [String],
+
+    /// Root file of the crate, the module of the crate itself.
+    root: Option<&'a Path>,
+
+    /// Directory of the root file of the crate, where its modules start.
+    root_dir: Option<&'a Path>,
+}
+
+/// Converts `files`, by canonical path, in parallel. Files that are not part
+/// of a target use the default crate.
+///
+/// Returns what must be displayed about each file or an error message, in
+/// the order of the files. With `--staged`, the written files are staged
+/// again, a failure to do so being reported last.
+fn run_for_files(
+    files: BTreeMap<PathBuf, (&Path, Option<usize>)>,
+    run_config: &RunConfig,
+    crate_infos: &HashMap<usize, CrateInfo>,
+) -> Vec<Result<String, String>> {
+    let files: Vec<_> = files.into_iter().collect();
+    let reports: Vec<_> = files
+        .par_iter()
+        .map(|(canonical, (path, target))| match target {
+            Some(idx) => run_for_file(path, run_config, &crate_infos[idx]),
+            None => {
+                let crate_info = CrateInfo {
+                    krate: run_config.default_crate.to_string(),
+                    dependencies: run_config.deps.for_path(canonical),
+                    ..Default::default()
+                };
+                run_for_file(path, run_config, &crate_info)
             }
-            run_for_file(&path, &args, &file_config);
+        })
+        .collect();
+
+    // Several `git add` running at once would fight over the lock of the
+    // index, the files are staged together once they are all written. They
+    // had no unstaged changes so the index and the working tree stay the
+    // same.
+    let written: Vec<_> = reports
+        .iter()
+        .filter_map(|r| r.as_ref().ok()?.written.clone())
+        .collect();
+    let mut reports: Vec<_> = reports.into_iter().map(|r| r.map(|r| r.output)).collect();
+    if let Some(repo) = run_config.repo.filter(|_| run_config.args.staged) {
+        if let Err(e) = repo.stage(&written) {
+            reports.push(Err(format!(
+                "Failed to stage the written files -- error: {}",
+                e
+            )));
+        }
+    }
+
+    reports
+}
+
+/// Result of `run_for_file`.
+#[derive(Debug)]
+struct FileReport {
+    /// What must be displayed about the file.
+    output: String,
+
+    /// Canonical path of the file when its changes were written to it.
+    written: Option<PathBuf>,
+}
+
+/// Converts the file at `path`, which is either absolute or relative to the
+/// directory `cargo-intraconv` was started in.
+///
+/// Returns what must be displayed about the file or an error message.
+fn run_for_file(
+    path: &Path,
+    run_config: &RunConfig,
+    crate_info: &CrateInfo,
+) -> Result<FileReport, String> {
+    let args = run_config.args;
+
+    // Paths with several components in the configuration file are
+    // canonicalized, the path of the file must be too to match them.
+    let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    // First display the path of the file that is about to be opened and tested,
+    // relative to where the user started when possible.
+    let path_display = path
+        .strip_prefix(run_config.start_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string();
+
+    let error = |msg: &str, e: &dyn std::fmt::Display| {
+        format!("{} '{}' -- error: {}", msg, path_display, e)
+    };
+
+    // Then open the file, reporting if it fails.
+    let file: Box<dyn BufRead> = match run_config.repo {
+        Some(repo) if args.staged => Box::new(Cursor::new(
+            repo.staged_content(&canonical_path)
+                .map_err(|e| error("Failed to read the staged content of", &e))?,
+        )),
+        _ => Box::new(BufReader::new(
+            File::open(path).map_err(|e| error("Failed to open file for reading", &e))?,
+        )),
+    };
+    let mut actions = convert(path, &canonical_path, file, run_config, crate_info)
+        .map_err(|e| error("Failed to transform file", &e))?;
+
+    if let Some(changed_lines) = run_config.changed_lines {
+        let lines = changed_lines
+            .get(&canonical_path)
+            .map_or(&[][..], Vec::as_slice);
+        actions = actions
+            .into_iter()
+            .map(|a| match a.pos() {
+                Some(pos) if !lines.iter().any(|l| l.contains(&pos)) => a.into_unchanged(),
+                _ => a,
+            })
+            .collect();
+    }
+
+    let has_changes = actions
+        .iter()
+        .any(|a| matches!(a, Action::Replaced { .. } | Action::Deleted { .. }));
+
+    // The checkout is never modified when writing to another directory.
+    let out_path = match run_config.out_dir {
+        Some((out_dir, base_dir)) => Some(
+            out_dir.join(
+                canonical_path
+                    .strip_prefix(base_dir)
+                    .map_err(|_| error("Cannot mirror", &"outside of the base directory"))?,
+            ),
+        ),
+        None => None,
+    };
+
+    // Writing the staged content over the file would lose its unstaged
+    // changes, and there is nothing to write when nothing changed.
+    let mut apply = args.apply && out_path.is_none();
+    if let Some(repo) = run_config.repo.filter(|_| apply && args.staged) {
+        apply = has_changes;
+        let unstaged = repo
+            .has_unstaged_changes(&canonical_path)
+            .map_err(|e| error("Failed to find the unstaged changes of", &e))?;
+        if apply && unstaged {
+            return Err(error(
+                "Failed to write changes to",
+                &"the file has unstaged changes",
+            ));
         }
     }
+
+    let output = if args.quiet {
+        String::new()
+    } else {
+        display_actions(&actions, &crate_info.krate, &path_display)
+    };
+
+    if apply {
+        write_atomically(path, &new_content(&actions), path)
+            .map_err(|e| error("Failed to write changes to", &e))?;
+    }
+
+    if let Some(out_path) = out_path.filter(|_| has_changes || args.copy_unchanged) {
+        let write = || {
+            std::fs::create_dir_all(out_path.parent().unwrap_or(&out_path))?;
+            write_atomically(&out_path, &new_content(&actions), path)
+        };
+        write().map_err(|e| error(&format!("Failed to write '{}' for", out_path.display()), &e))?;
+    }
+
+    Ok(FileReport {
+        output,
+        written: Some(canonical_path).filter(|_| apply),
+    })
+}
+
+/// Converts the content read from `stdin` as if it was the file at
+/// `filename` (absolute), if any, writing the result to `stdout`.
+///
+/// Nothing is written on disk and the changes are displayed on `stderr`.
+fn run_for_stdin(
+    filename: Option<&Path>,
+    run_config: &RunConfig,
+    crate_info: &CrateInfo,
+) -> std::io::Result<()> {
+    let path = filename.unwrap_or_else(|| Path::new(""));
+
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+
+    let actions = convert(path, path, input.as_bytes(), run_config, crate_info)?;
+
+    if !run_config.args.quiet {
+        let path_display = match filename {
+            Some(f) => f
+                .strip_prefix(run_config.start_dir)
+                .unwrap_or(f)
+                .display()
+                .to_string(),
+            None => "<stdin>".to_string(),
+        };
+        eprint!(
+            "{}",
+            display_actions(&actions, &crate_info.krate, &path_display)
+        );
+    }
+
+    print!("{}", new_content(&actions));
+    std::io::stdout().flush()
 }
 
-fn run_for_file(path: &Path, args: &CliArgs, file_config: &FileConfig) {
-    let krate = Krate::new(&args.krate).expect("Not a valid Rust identifier");
+/// Finds the actions for the lines of `reader`, the content of the file at
+/// `path`.
+fn convert(
+    path: &Path,
+    canonical_path: &Path,
+    reader: impl BufRead,
+    run_config: &RunConfig,
+    crate_info: &CrateInfo,
+) -> std::io::Result<Vec<Action>> {
+    let args = run_config.args;
+    let krate = Krate::new(&crate_info.krate).expect("Not a valid Rust identifier");
+
+    // The root file of the crate does not define a module, even when its
+    // name is not `lib.rs` or `main.rs`, like `src/bin/tool.rs`.
+    let modules = if crate_info.root == Some(canonical_path) {
+        Vec::new()
+    } else {
+        // Listed paths can be relative, the root is always absolute.
+        let module_path = crate_info
+            .root_dir
+            .and_then(|root| canonical_path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        sections::module_path(module_path)
+    };
+    let doc_dir = run_config
+        .doc_root
+        .map(|root| sections::module_doc_dir(root, krate.name(), &modules));
 
     let opts = ConversionOptions {
         krate,
         disambiguate: args.disambiguate,
         favored_links: !args.no_favored,
-        ignored_links: file_config,
-        current_path: &path,
+        ignored_links: run_config.file_config,
+        current_path: canonical_path,
+        doc_dir: doc_dir.as_deref(),
+        html_root_url: crate_info.html_root_url.as_deref(),
+        doc_roots: &run_config.deps.doc_roots,
+        dependencies: crate_info.dependencies,
+        facade: crate_info.facade,
+        workspace_crates: crate_info.workspace_crates,
+        module_depth: modules.len(),
     };
 
-    let display_changes = !args.quiet;
-    let mut ctx = ConversionContext::with_options(opts);
+    ConversionContext::with_options(opts).transform_file(reader)
+}
+
+/// Displays the changes and warnings of `actions`, nothing when there are
+/// none.
+fn display_actions(actions: &[Action], krate: &str, path_display: &str) -> String {
+    let mut output = String::new();
 
-    // First display the path of the file that is about to be opened and tested.
-    let path_display = path.display().to_string();
+    if actions.iter().all(Action::is_unchanged) {
+        return output;
+    }
 
-    // Then open the file, reporting if it fails.
-    let file = BufReader::new(return_error!(
-        File::open(&path),
-        "Failed to open file '{}' for reading",
-        &path_display
-    ));
-    let actions = return_error!(
-        ctx.transform_file(file),
-        "Failed to transform file '{}'",
-        &path_display
-    );
-
-    // Do not allocate when unecessary.
-    let mut updated_content = if args.apply {
-        String::with_capacity(64 * actions.len())
-    } else {
-        String::new()
-    };
+    writeln!(
+        output,
+        "{}: {}\n{}\n",
+        krate,
+        path_display,
+        "=".repeat(krate.len() + 1)
+    )
+    .unwrap();
 
-    // Only display the filename when -q is not set and there are changes.
-    if display_changes && actions.iter().any(|a| !a.is_unchanged()) {
-        println!(
-            "{}: {}\n{}\n",
-            &args.krate,
-            &path_display,
-            "=".repeat(args.krate.len() + 1)
-        );
+    for l in actions.iter().filter(|l| !l.is_unchanged()) {
+        writeln!(output, "{}\n", l).unwrap();
     }
 
-    // Display the changes that can be made.
+    output
+}
+
+/// Content of the file after applying `actions`.
+fn new_content(actions: &[Action]) -> String {
+    let mut content = String::with_capacity(64 * actions.len());
     for l in actions {
-        if !l.is_unchanged() && display_changes {
-            println!("{}\n", l);
-        }
-        if args.apply {
-            write!(updated_content, "{}", l.as_new_line()).unwrap();
-        }
+        content.push_str(l.as_new_line());
     }
+    content
+}
 
-    if !args.apply {
-        return;
+/// Replaces the content of the file at `path`, giving it the permissions of
+/// the file at `source`.
+///
+/// The content is written to a temporary file in the same directory which is
+/// then renamed, so the file is never left partially written. Symbolic links
+/// are resolved first so the file they point to is replaced, not them.
+///
+/// The temporary file belongs to the current user: when the existing file
+/// belongs to someone else, it is written in place instead to keep its owner
+/// and group.
+fn write_atomically(path: &Path, content: &str, source: &Path) -> std::io::Result<()> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    if let Ok(existing) = std::fs::metadata(&path) {
+        if !same_owner(&existing, &tmp.as_file().metadata()?) {
+            drop(tmp);
+            return std::fs::write(&path, content);
+        }
     }
 
-    let mut file = return_error!(
-        OpenOptions::new().write(true).truncate(true).open(path),
-        "Failed to open file '{}' for writing",
-        &path_display
-    );
+    tmp.write_all(content.as_bytes())?;
+    tmp.as_file()
+        .set_permissions(std::fs::metadata(source)?.permissions())?;
+    tmp.persist(&path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn same_owner(a: &std::fs::Metadata, b: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.uid(), a.gid()) == (b.uid(), b.gid())
+}
 
-    return_error!(
-        write!(file, "{}", updated_content),
-        "Failed to write changes to '{}'",
-        &path_display
-    );
+#[cfg(not(unix))]
+fn same_owner(_: &std::fs::Metadata, _: &std::fs::Metadata) -> bool {
+    true
 }
@@ -1,8 +1,13 @@
 use crate::ConversionOptions;
+use crate::Facade;
 use crate::Krate;
+use crate::{FavoredHost, HostSegment};
 
 use regex::Regex;
+use semver::{Version, VersionReq};
 use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fmt;
 use std::path::{Component, Path};
 
 pub fn link_parts<'a>(
@@ -10,8 +15,12 @@ pub fn link_parts<'a>(
     opts: &ConversionOptions,
 ) -> Result<LinkParts<'a>, &'a std::ffi::OsStr> {
     favored_parts(path, opts)
-        .or_else(|| start_middle_end(path, &opts.krate))
-        .ok_or_else(|| path.as_os_str())
+        .or_else(|| {
+            let parts = start_middle_end(path, &opts.krate)?;
+            parts.with_other_crate(opts)
+        })
+        .map(|parts| parts.with_lib_name(opts))
+        .ok_or(path.as_os_str())
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -28,13 +37,12 @@ impl<'a> LinkParts<'a> {
         match self.start {
             Start::Empty => (),
             Start::Local => {
-                let needs_type_block = matches!((&self.end, self.modules), (End::Assoc(_), None));
-                if needs_type_block {
-                    result.push_str(ctx.current_type_block().unwrap_or("Self"))
+                if self.needs_assoc_parent() {
+                    result.push_str(ctx.assoc_parent().unwrap_or("Self"))
                 }
             }
             Start::Crate => result.push_str("crate"),
-            Start::Mod(s) => result.push_str(s),
+            Start::Mod(ref s) => result.push_str(s),
             Start::Supers(n) => {
                 for _ in 0..(n - 1) {
                     result.push_str("super::");
@@ -56,11 +64,11 @@ impl<'a> LinkParts<'a> {
                 result.push('#');
                 result.push_str(name);
             }
-            End::Assoc(AssociatedItem { dis: _, name }) => {
+            End::Assoc(assoc) => {
                 if !result.is_empty() {
                     result.push_str("::");
                 }
-                result.push_str(name);
+                assoc.push_path(&mut result);
             }
             End::Module { name, section } => {
                 if !result.is_empty() {
@@ -97,9 +105,9 @@ impl<'a> LinkParts<'a> {
                         result.push('#');
                         result.push_str(name);
                     }
-                    Some(AssocOrSection::Assoc(AssociatedItem { dis: _, name })) => {
+                    Some(AssocOrSection::Assoc(assoc)) => {
                         result.push_str("::");
-                        result.push_str(name);
+                        assoc.push_path(&mut result);
                     }
                 }
             }
@@ -110,11 +118,14 @@ impl<'a> LinkParts<'a> {
                 result.insert_str(0, s);
             }
         } else if let Disambiguator::Suffix(s) = self.dis() {
-            let disambiguation_already_done = matches!(&self.end, End::Item {
-                dis: _,
-                name: _,
-                added: Some(AssocOrSection::Section(_)),
-            });
+            let disambiguation_already_done = matches!(
+                &self.end,
+                End::Item {
+                    dis: _,
+                    name: _,
+                    added: Some(AssocOrSection::Section(_)),
+                }
+            );
 
             if !disambiguation_already_done {
                 result.push_str(s);
@@ -124,6 +135,273 @@ impl<'a> LinkParts<'a> {
         result
     }
 
+    /// Returns a warning to display alongside the transformed link when the
+    /// conversion is known to be less reliable than usual.
+    pub fn warning(&self) -> Option<&'static str> {
+        let numbered = match &self.end {
+            End::Assoc(assoc)
+            | End::Item {
+                added: Some(AssocOrSection::Assoc(assoc)),
+                ..
+            } => assoc.numbered,
+            _ => false,
+        };
+
+        if numbered {
+            Some("Numbered anchor: the impl it pointed to is lost, check the new link targets the right item")
+        } else {
+            None
+        }
+    }
+
+    /// `true` when the link is to an associated item of the current type,
+    /// like `#method.name`.
+    pub fn needs_assoc_parent(&self) -> bool {
+        matches!(
+            (&self.start, self.modules, &self.end),
+            (Start::Local, None, End::Assoc(_))
+        )
+    }
+
+    /// `true` when the link ends with a section, like `#examples`.
+    pub fn has_section(&self) -> bool {
+        matches!(
+            &self.end,
+            End::Section(_)
+                | End::Module {
+                    section: Some(_),
+                    ..
+                }
+                | End::Item {
+                    added: Some(AssocOrSection::Section(_)),
+                    ..
+                }
+        )
+    }
+
+    /// `true` when the link points to an `#impl-...` or `#deref-methods-...`
+    /// anchor.
+    pub fn is_impl_anchor(&self) -> bool {
+        self.impl_anchor(None).is_some()
+    }
+
+    /// Transforms a link to an `#impl-...` or `#deref-methods-...` anchor to
+    /// a link to the trait, the deref target or the type itself (for inherent
+    /// impls).
+    ///
+    /// Returns `None` when it is not such a link or when the target cannot be
+    /// found from the anchor alone.
+    pub fn transform_impl_target(&self, ctx: &crate::ConversionContext) -> Option<String> {
+        let item = match &self.end {
+            End::Item { name, .. } => Some(*name),
+            _ => ctx.assoc_parent().filter(|&ty| ty != "Self"),
+        };
+
+        match self.impl_anchor(item)? {
+            ImplAnchor::Inherent => match &self.end {
+                End::Item { dis, name, .. } => {
+                    let parts = LinkParts {
+                        start: self.start.clone(),
+                        modules: self.modules,
+                        end: End::Item {
+                            dis: *dis,
+                            name,
+                            added: None,
+                        },
+                    };
+                    Some(parts.transform(ctx))
+                }
+                _ => item.map(String::from),
+            },
+            ImplAnchor::Trait(name) => {
+                let mut result = String::with_capacity(name.len() + 5);
+                if let Disambiguator::Prefix(s) = Disambiguator::from("trait") {
+                    if ctx.options().disambiguate {
+                        result.push_str(s);
+                    }
+                }
+                result.push_str(name);
+                Some(result)
+            }
+            ImplAnchor::Deref(target) => target.map(String::from),
+        }
+    }
+
+    /// Finds the impl anchor of the link, if any.
+    ///
+    /// `item` is the type whose page holds the anchor, when known. It is used
+    /// to tell inherent impls from trait impls for `#impl-Name` anchors.
+    fn impl_anchor(&self, item: Option<&str>) -> Option<ImplAnchor<'a>> {
+        match &self.end {
+            End::Item {
+                added: Some(AssocOrSection::Section(Section { name })),
+                ..
+            } => ImplAnchor::new(name, item),
+            End::Section(Section { name })
+                if self.start == Start::Local && self.modules.is_none() =>
+            {
+                ImplAnchor::new(name, item)
+            }
+            _ => None,
+        }
+    }
+
+    /// Rewrites a link into `std`, `alloc` or `core` to the crate defining the
+    /// item when the current crate has no access to `std`, see `Facade`.
+    ///
+    /// Returns a warning when the item is not available to the crate.
+    pub fn with_facade(self, opts: &ConversionOptions) -> Result<Self, &'static str> {
+        if opts.facade == Facade::Std {
+            return Ok(self);
+        }
+
+        let linked = match self.crate_name().and_then(Facade::from_crate) {
+            Some(linked) => linked,
+            None => return Ok(self),
+        };
+
+        let location = match linked {
+            Facade::Std => crate::facade::std_location(&self.path_in_crate()),
+            other => other,
+        };
+
+        if location > opts.facade {
+            return Err(match location {
+                Facade::Alloc => "Only available with `extern crate alloc;`, left unchanged",
+                _ => "Not available without `std`, left unchanged",
+            });
+        }
+
+        let name = if location.name() == opts.krate.name() {
+            "crate"
+        } else {
+            location.name()
+        };
+        Ok(self.with_crate_name(name.into()))
+    }
+
+    /// Transforms relative links to the documentation of another crate of the
+    /// workspace or of a standard crate, documented alongside the current
+    /// one, like `../b/struct.Client.html` or
+    /// `../../std/option/enum.Option.html`, into links to this crate.
+    ///
+    /// Only links leaving the documentation of the current crate are
+    /// concerned, others are links to its modules (see
+    /// `ConversionOptions::module_depth`).
+    ///
+    /// Returns `None` when the crate is not a dependency of the current one.
+    fn with_other_crate(self, opts: &ConversionOptions) -> Option<Self> {
+        let is_sibling = |name: &OsStr| {
+            opts.workspace_crates.iter().any(|c| name == c.as_str())
+                || name.to_str().and_then(Facade::from_crate).is_some()
+        };
+
+        let (start, modules) = match (&self.start, self.modules, &self.end) {
+            (Start::Supers(supers), _, _) if *supers <= opts.module_depth => return Some(self),
+            // ../b/struct.Client.html
+            (Start::Supers(_), Some(modules), _) => {
+                let first = match modules.components().next() {
+                    Some(Component::Normal(first)) if is_sibling(first) => first,
+                    _ => return Some(self),
+                };
+                let rest = modules.strip_prefix(first).expect("First component");
+
+                (
+                    Start::Mod(first.to_str()?.into()),
+                    Some(rest).filter(|r| r.components().next().is_some()),
+                )
+            }
+            // ../b/index.html
+            (Start::Supers(_), None, End::Module { name, .. })
+                if is_sibling(OsStr::new(name.as_ref())) =>
+            {
+                (Start::Empty, None)
+            }
+            _ => return Some(self),
+        };
+
+        let parts = LinkParts {
+            start,
+            modules,
+            end: self.end,
+        };
+        check_dependency(parts, opts)
+    }
+
+    /// Path of the linked item inside the crate the link starts with, like
+    /// `option::Option`. Macros end with a `!`.
+    fn path_in_crate(&self) -> String {
+        let mut path: Vec<&str> = self
+            .modules
+            .iter()
+            .flat_map(|m| m.components())
+            .filter_map(|c| match c {
+                Component::Normal(c) => c.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        match &self.end {
+            End::Item { name, .. } => path.push(name),
+            End::Module { name, .. } if self.start != Start::Empty => path.push(name),
+            _ => (),
+        }
+
+        let mut path = path.join("::");
+        if let End::Item {
+            dis: Disambiguator::Suffix("!"),
+            ..
+        } = &self.end
+        {
+            path.push('!');
+        }
+        path
+    }
+
+    /// Name of the crate the link starts with, if any.
+    fn crate_name(&self) -> Option<&str> {
+        match (&self.start, self.modules, &self.end) {
+            (Start::Mod(name), _, _) => Some(name),
+            (Start::Empty, None, End::Module { name, .. })
+                if !matches!(name.as_ref(), "crate" | "self" | "super") =>
+            {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Replaces the name of the crate the link starts with, see `crate_name`.
+    fn with_crate_name(mut self, new: String) -> Self {
+        match (&mut self.start, &mut self.end) {
+            (Start::Mod(name), _) | (Start::Empty, End::Module { name, .. }) => *name = new.into(),
+            _ => (),
+        }
+        self
+    }
+
+    /// Replaces `crate` by the name of the library when the current file is
+    /// part of another target of its package, like a binary with the same
+    /// name: the library is then one of its dependencies.
+    fn with_lib_name(mut self, opts: &ConversionOptions) -> Self {
+        let name = opts.krate.name();
+        let is_own_lib = opts
+            .dependencies
+            .is_some_and(|deps| deps.iter().any(|d| d.lib == name));
+        if !is_own_lib {
+            return self;
+        }
+
+        match (&mut self.start, self.modules, &mut self.end) {
+            (start @ Start::Crate, _, _) => *start = Start::Mod(name.to_string().into()),
+            (Start::Empty, None, End::Module { name: end, .. }) if end == "crate" => {
+                *end = name.to_string().into()
+            }
+            _ => (),
+        }
+        self
+    }
+
     fn dis(&self) -> Disambiguator {
         match self.end {
             // NOTE: maybe this could use a context to see if it should point
@@ -176,7 +454,7 @@ enum Start<'a> {
     Empty,
     Local,
     Crate,
-    Mod(&'a str),
+    Mod(Cow<'a, str>),
     Supers(usize),
 }
 
@@ -205,6 +483,28 @@ enum AssocOrSection<'a> {
 struct AssociatedItem<'a> {
     dis: Disambiguator,
     name: &'a str,
+    /// `true` when the anchor had a numbered suffix like `#method.new-1`.
+    ///
+    /// rustdoc adds those when several impls of a type have items with the
+    /// same name. The impl targeted by the original link is lost during the
+    /// conversion, so the result is less reliable.
+    numbered: bool,
+    /// Field of a struct-like variant, as in `#variant.Foo.field.bar`.
+    ///
+    /// When present, `name` is the name of the variant.
+    field: Option<&'a str>,
+}
+
+impl AssociatedItem<'_> {
+    /// Pushes the path of the associated item (without leading `::`) to
+    /// `result`.
+    fn push_path(&self, result: &mut String) {
+        result.push_str(self.name);
+        if let Some(field) = self.field {
+            result.push_str("::");
+            result.push_str(field);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -212,6 +512,57 @@ struct Section<'a> {
     name: &'a str,
 }
 
+/// Anchors generated by rustdoc for impl blocks.
+///
+/// They have no intra-doc link equivalent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ImplAnchor<'a> {
+    /// `#impl`, `#impl-1` or `#impl-Type`.
+    Inherent,
+    /// `#impl-Trait-for-Type` or `#impl-Trait` (older rustdoc versions).
+    ///
+    /// Generic parameters are percent-encoded by rustdoc, as in
+    /// `#impl-From%3CT%3E-for-Foo`, they are not part of the name.
+    Trait(&'a str),
+    /// `#deref-methods-Target`. The target is unknown for older rustdoc
+    /// versions, which only produced `#deref-methods`.
+    Deref(Option<&'a str>),
+}
+
+impl<'a> ImplAnchor<'a> {
+    /// Parses the section name (without `#`) as an impl anchor.
+    ///
+    /// `item` is the type whose page holds the anchor, when known.
+    fn new(section: &'a str, item: Option<&str>) -> Option<Self> {
+        lazy_static::lazy_static! {
+            static ref IMPL: Regex = Regex::new(&format!(
+                r"^impl(?:-(?P<name>{rid})(?:%3[Cc].*?%3[Ee])?(?P<for>-for-.+?)?)?(?:-\d+)?$",
+                rid = crate::RUST_IDENTIFIER,
+            )).unwrap();
+
+            static ref DEREF: Regex = Regex::new(&format!(
+                r"^deref-methods(?:-(?P<target>{rid}))?$",
+                rid = crate::RUST_IDENTIFIER,
+            )).unwrap();
+        }
+
+        if let Some(captures) = DEREF.captures(section) {
+            let target = captures.name("target").map(|t| t.as_str());
+            return Some(Self::Deref(target));
+        }
+
+        let captures = IMPL.captures(section)?;
+        match captures.name("name").map(|n| n.as_str()) {
+            None => Some(Self::Inherent),
+            // Recent rustdoc versions use `#impl-Type` for inherent impls.
+            Some(name) if captures.name("for").is_none() && Some(name) == item => {
+                Some(Self::Inherent)
+            }
+            Some(name) => Some(Self::Trait(name)),
+        }
+    }
+}
+
 fn favored_parts<'a>(path: &'a Path, opts: &ConversionOptions) -> Option<LinkParts<'a>> {
     fn is_http(path: &Path) -> bool {
         (path.starts_with("http:") || path.starts_with("https:")) && path.components().count() >= 2
@@ -221,6 +572,21 @@ fn favored_parts<'a>(path: &'a Path, opts: &ConversionOptions) -> Option<LinkPar
         return None;
     }
 
+    if let Some(parts) = opts
+        .html_root_url
+        .and_then(|root| favored_html_root(path, Path::new(root), opts.krate.name(), &opts.krate))
+    {
+        return check_dependency(parts, opts);
+    }
+
+    if let Some(parts) = opts
+        .doc_roots
+        .iter()
+        .find_map(|root| favored_html_root(path, Path::new(&root.url), &root.krate, &opts.krate))
+    {
+        return check_dependency(parts, opts);
+    }
+
     let comp_count = path.components().count();
 
     let mut comps = path.components();
@@ -231,62 +597,84 @@ fn favored_parts<'a>(path: &'a Path, opts: &ConversionOptions) -> Option<LinkPar
     const DOC_RUST_LANG_ORG: &str = "doc.rust-lang.org";
 
     // Checking the domain for favored links patterns.
-    match domain {
+    let parts = match domain {
         // https://docs.rs/regex
         Component::Normal(dom) if dom == DOCS_RS && comp_count >= 3 => {
             favored_docs_rs(path, &opts.krate)
         }
+        // The standard crates are always available.
         Component::Normal(dom) if dom == DOC_RUST_LANG_ORG && comp_count >= 3 => {
-            favored_doc_rust_lang_org(path, &opts.krate)
+            return favored_doc_rust_lang_org(path, &opts.krate);
         }
+        Component::Normal(dom) => opts
+            .favored_hosts()
+            .iter()
+            .filter(|host| dom == host.domain())
+            .find_map(|host| favored_host(path, host, &opts.krate)),
         _ => None,
-    }
-}
+    }?;
 
-fn favored_docs_rs<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
-    let mut comps = path.components();
-    let http = comps.next()?;
-    debug_assert!([
-        Component::Normal("http:".as_ref()),
-        Component::Normal("https:".as_ref())
-    ]
-    .contains(&http));
-    let domain = comps.next()?;
-    debug_assert_eq!(Component::Normal("docs.rs".as_ref()), domain);
+    check_dependency(parts, opts)
+}
 
-    let untreated = path
-        .strip_prefix(http)
-        .expect("Stripping http")
-        .strip_prefix(domain)
-        .expect("Stripping domain name");
+/// Ensures a favored link points into the current crate, one of the
+/// dependencies available when building its docs or a standard crate, using
+/// the name of the dependency in Rust code.
+///
+/// Nothing is checked when the dependencies are unknown.
+fn check_dependency<'a>(parts: LinkParts<'a>, opts: &ConversionOptions) -> Option<LinkParts<'a>> {
+    let deps = match opts.dependencies {
+        Some(deps) => deps,
+        None => return Some(parts),
+    };
 
-    let crate_name = comps.next()?.as_os_str();
+    let lib = match parts.crate_name() {
+        Some(lib) if lib != opts.krate.name() && Facade::from_crate(lib).is_none() => lib,
+        _ => return Some(parts),
+    };
 
-    // https://docs.rs/crate/regex/ is NOT a link to the documentation for
-    // the `regex` crate.
-    if crate_name == "crate" {
-        return None;
+    let dep = deps.iter().find(|dep| dep.lib == lib)?;
+    if dep.name == lib {
+        Some(parts)
+    } else {
+        Some(parts.with_crate_name(dep.name.clone()))
     }
+}
 
-    let crate_only_link_parts = || {
-        let start = Start::Empty;
-        let modules = None;
+/// Link parts for a link to the root of a crate's documentation, like
+/// `https://docs.rs/regex`.
+fn crate_only_link_parts<'a>(crate_name: &'a OsStr, krate: &Krate) -> Option<LinkParts<'a>> {
+    let start = Start::Empty;
+    let modules = None;
 
-        // Early return that avoids making a conversion to UTF-8 when
-        // possible.
-        if crate_name == krate.name() {
-            return Some(LinkParts {
-                start,
-                modules,
-                end: End::Module {
-                    name: "crate".into(),
-                    section: None,
-                },
-            });
-        }
+    // Early return that avoids making a conversion to UTF-8 when
+    // possible.
+    if crate_name == krate.name() {
+        return Some(LinkParts {
+            start,
+            modules,
+            end: End::Module {
+                name: "crate".into(),
+                section: None,
+            },
+        });
+    }
 
-        let crate_name = crate_name.to_str()?;
-        if crate::RUST_IDENTIFIER_RE.is_match(crate_name) {
+    let crate_name = crate_name.to_str()?;
+    if crate::RUST_IDENTIFIER_RE.is_match(crate_name) {
+        Some(LinkParts {
+            start,
+            modules,
+            end: End::Module {
+                name: crate_name.into(),
+                section: None,
+            },
+        })
+    } else {
+        // Attempts to fix the crate name to be a valid Rust
+        // identifier.
+        let crate_name = crate_name.replace('-', "_");
+        if crate::RUST_IDENTIFIER_RE.is_match(&crate_name) {
             Some(LinkParts {
                 start,
                 modules,
@@ -296,51 +684,254 @@ fn favored_docs_rs<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
                 },
             })
         } else {
-            // Attempts to fix the crate name to be a valid Rust
-            // identifier.
-            let crate_name = crate_name.replace('-', "_");
-            if crate::RUST_IDENTIFIER_RE.is_match(&crate_name) {
-                Some(LinkParts {
-                    start,
-                    modules,
-                    end: End::Module {
-                        name: crate_name.into(),
-                        section: None,
-                    },
-                })
-            } else {
-                None
-            }
+            None
         }
-    };
+    }
+}
 
-    let version = match comps.next() {
-        Some(v) => {
-            lazy_static::lazy_static! {
-                static ref VERSION_REGEX: Regex = Regex::new(r"(?:\d+\.\d+\.\d+|latest)").unwrap();
-            }
-            let v = v.as_os_str().to_str().unwrap_or("");
-            if !VERSION_REGEX.is_match(v) {
-                return None;
+/// Handles links under the `html_root_url` of `root_crate`, either the
+/// current crate or one of its dependencies.
+///
+/// The root contains a directory for each documented crate, `root_crate`
+/// included, like `https://docs.rs/regex/1.4.2`.
+fn favored_html_root<'a>(
+    path: &'a Path,
+    root: &Path,
+    root_crate: &str,
+    krate: &Krate,
+) -> Option<LinkParts<'a>> {
+    let untreated = path.strip_prefix(root).ok()?;
+
+    // https://docs.example and https://docs.example/krate
+    if untreated.components().next().is_none() || untreated == Path::new(root_crate) {
+        let name = if root_crate == krate.name() {
+            "crate".into()
+        } else {
+            root_crate.to_string().into()
+        };
+
+        return Some(LinkParts {
+            start: Start::Empty,
+            modules: None,
+            end: End::Module {
+                name,
+                section: None,
+            },
+        });
+    }
+
+    start_middle_end(untreated, krate)
+}
+
+/// Handles links to a favored host from the configuration file.
+///
+/// The segments of the host are matched first and the rest of the path is
+/// then treated like a relative link from the root of the documentation.
+fn favored_host<'a>(path: &'a Path, host: &FavoredHost, krate: &Krate) -> Option<LinkParts<'a>> {
+    let mut comps = path.components();
+    let _http = comps.next()?;
+    let _domain = comps.next()?;
+
+    let mut crate_name = None;
+    for segment in host.segments() {
+        let comp = match comps.next() {
+            Some(c) => c.as_os_str(),
+            // https://docs.internal.example/regex/
+            None => return crate_only_link_parts(crate_name?, krate),
+        };
+
+        match segment {
+            HostSegment::Crate => crate_name = Some(comp),
+            HostSegment::Version | HostSegment::Channel => (),
+            HostSegment::Literal(lit) if comp == lit => (),
+            HostSegment::Literal(_) => return None,
+        }
+    }
+
+    let untreated = comps.as_path();
+    if untreated.components().next().is_none() {
+        return crate_only_link_parts(crate_name?, krate);
+    }
+
+    start_middle_end(untreated, krate)
+}
+
+/// A link to the documentation of a crate on `docs.rs`, like
+/// `https://docs.rs/regex/1.4.2/regex/struct.Regex.html`.
+///
+/// The full form is `https://docs.rs/<crate>/<version>/<platform>/<path>`,
+/// where everything after the crate name is optional.
+struct DocsRsLink<'a> {
+    /// Name of the crate, as in the URL.
+    krate: &'a OsStr,
+
+    /// Version of the crate, when present.
+    version: Option<DocsRsVersion>,
+
+    /// Rest of the path, relative to the root of the documentation.
+    rest: &'a Path,
+}
+
+/// Version segment of a `docs.rs` link.
+#[derive(Debug, Clone, PartialEq)]
+enum DocsRsVersion {
+    /// `latest` or `*`.
+    Latest,
+    /// An exact version, like `1.4.2`, `1.0.0-alpha.3` or `1.0.0+build`.
+    Exact(Version),
+    /// A version requirement, like `^1.2`, `~1` or `1.2`: `docs.rs` shows
+    /// the latest version matching it.
+    Req(VersionReq),
+}
+
+impl DocsRsVersion {
+    fn new(segment: &str) -> Option<Self> {
+        lazy_static::lazy_static! {
+            // Letters are only allowed for pre-release and build metadata:
+            // `badge.svg` must be refused.
+            static ref VERSION_REGEX: Regex =
+                Regex::new(r"^[\^~=<>]*\s*\d+(?:\.\d+){0,2}(?:[-+][0-9A-Za-z.+-]+)?$").unwrap();
+        }
+
+        // Requirements can be percent-encoded, like `%5E1.2` for `^1.2`.
+        let segment = percent_encoding::percent_decode_str(segment).decode_utf8_lossy();
+        match segment.as_ref() {
+            "latest" | "*" => Some(Self::Latest),
+            s if !VERSION_REGEX.is_match(s) => None,
+            s => match Version::parse(s) {
+                Ok(v) => Some(Self::Exact(v)),
+                Err(_) => VersionReq::parse(s).ok().map(Self::Req),
+            },
+        }
+    }
+
+    /// Checks if the version shown by `docs.rs` can be compatible with the
+    /// `locked` one.
+    fn is_compatible_with(&self, locked: &Version) -> bool {
+        match self {
+            Self::Latest => true,
+            Self::Exact(v) => is_compatible(v, locked),
+            // `~1.3` does not match `1.4.2` but shows the same API.
+            Self::Req(req) => {
+                req.matches(locked) || req_base(req).is_none_or(|v| is_compatible(&v, locked))
             }
+        }
+    }
+}
 
-            v
+impl fmt::Display for DocsRsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => f.write_str("latest"),
+            Self::Exact(v) => v.fmt(f),
+            Self::Req(req) => req.fmt(f),
         }
-        None => return crate_only_link_parts(),
-    };
+    }
+}
 
-    match comps.next().map(|x| x.as_os_str()) {
-        Some(_) => (),
-        None => return crate_only_link_parts(),
+impl<'a> DocsRsLink<'a> {
+    /// Parses the link, returning `None` if it is not a link to the
+    /// documentation of a crate on `docs.rs`.
+    fn new(path: &'a Path) -> Option<Self> {
+        lazy_static::lazy_static! {
+            // Crate directories are identifiers and cannot contain a `-`.
+            static ref PLATFORM_REGEX: Regex =
+                Regex::new(r"^[a-z0-9_]+(?:-[a-z0-9_]+){1,3}$").unwrap();
+        }
+
+        let mut comps = path.components();
+        let http = comps.next()?;
+        if http != Component::Normal("http:".as_ref())
+            && http != Component::Normal("https:".as_ref())
+        {
+            return None;
+        }
+        if comps.next()? != Component::Normal("docs.rs".as_ref()) {
+            return None;
+        }
+
+        let krate = comps.next()?.as_os_str();
+
+        // https://docs.rs/crate/regex/ is NOT a link to the documentation for
+        // the `regex` crate.
+        if krate == "crate" {
+            return None;
+        }
+
+        let version = match comps.next() {
+            Some(v) => Some(DocsRsVersion::new(v.as_os_str().to_str()?)?),
+            None => None,
+        };
+
+        // https://docs.rs/regex/1.4.2/x86_64-unknown-linux-gnu/regex/
+        let mut rest = comps.as_path();
+        if let Some(Component::Normal(platform)) = comps.next() {
+            if PLATFORM_REGEX.is_match(platform.to_str()?) {
+                rest = comps.as_path();
+            }
+        }
+
+        Some(Self {
+            krate,
+            version,
+            rest,
+        })
     }
+}
 
-    let untreated = untreated
-        .strip_prefix(crate_name)
-        .expect("Removing crate first identifier")
-        .strip_prefix(version)
-        .expect("Removing version");
+fn favored_docs_rs<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
+    let link = DocsRsLink::new(path)?;
 
-    start_middle_end(untreated, krate)
+    if link.rest.components().next().is_none() {
+        crate_only_link_parts(link.krate, krate)
+    } else {
+        start_middle_end(link.rest, krate)
+    }
+}
+
+/// Returns a warning when `path` is a link to `docs.rs` for a version of a
+/// dependency that is not compatible with the one in `Cargo.lock`.
+pub fn docs_rs_version_mismatch(path: &Path, opts: &ConversionOptions) -> Option<String> {
+    let link = DocsRsLink::new(path)?;
+    let version = link.version?;
+
+    let lib = link.krate.to_str()?.replace('-', "_");
+    let dep = opts.dependencies?.iter().find(|dep| dep.lib == lib)?;
+    let locked = dep.version.as_ref()?;
+
+    if version.is_compatible_with(locked) {
+        None
+    } else {
+        Some(format!(
+            "Links to version {} of '{}' but version {} is used in Cargo.lock",
+            version, dep.name, locked
+        ))
+    }
+}
+
+/// Checks if two versions are compatible according to the rules of cargo.
+fn is_compatible(a: &Version, b: &Version) -> bool {
+    match (a.major, b.major) {
+        (0, 0) if a.minor == 0 && b.minor == 0 => a.patch == b.patch,
+        (0, 0) => a.minor == b.minor,
+        (a, b) => a == b,
+    }
+}
+
+/// Lowest version mentioned by a requirement, like `1.2.0` for `^1.2`.
+fn req_base(req: &VersionReq) -> Option<Version> {
+    let req = req.to_string();
+    let first = req.split(',').next()?;
+    let first = first.trim_start_matches(|c: char| "^~=<> ".contains(c));
+
+    let mut parts = first.splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+    let patch = parts
+        .next()
+        .map_or(Some(0), |p| p.split(['-', '+']).next()?.parse().ok())?;
+
+    Some(Version::new(major, minor, patch))
 }
 
 fn favored_doc_rust_lang_org<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
@@ -363,14 +954,8 @@ fn favored_doc_rust_lang_org<'a>(path: &'a Path, krate: &Krate) -> Option<LinkPa
     // Ensure the channel can be converted to a valid "&str".
     let channel_or_crate = comps.next()?.as_os_str();
 
-    const CRATES: [&str; 6] = [
-        "std",
-        "alloc",
-        "core",
-        "test",
-        "proc_macro",
-        "nightly-rustc",
-    ];
+    const CRATES: [&str; 5] = ["std", "alloc", "core", "test", "proc_macro"];
+    const NIGHTLY_RUSTC: &str = "nightly-rustc";
     const CHANNELS: [&str; 3] = ["nightly", "beta", "stable"];
     lazy_static::lazy_static! {
         static ref VERSION_REGEX: Regex = Regex::new(r"1\.\d+\.\d+").unwrap();
@@ -390,25 +975,28 @@ fn favored_doc_rust_lang_org<'a>(path: &'a Path, krate: &Krate) -> Option<LinkPa
         (channel_or_crate, untreated)
     };
 
-    if !CRATES.iter().any(|c| c == &linked_crate) {
-        return None;
-    }
+    // The compiler crates are all documented under `nightly-rustc`, like
+    // https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.TyCtxt.html
+    let (linked_crate, untreated) = if linked_crate == NIGHTLY_RUSTC {
+        let compiler_crate = comps.next()?.as_os_str();
+        if !crate::RUST_IDENTIFIER_RE.is_match(compiler_crate.to_str()?) {
+            return None;
+        }
 
-    if untreated.components().next().is_none() {
-        let k = if linked_crate == krate.name() {
-            "crate"
-        } else {
-            linked_crate.to_str()?
-        };
+        (
+            compiler_crate,
+            untreated
+                .strip_prefix(NIGHTLY_RUSTC)
+                .expect("Stripping nightly-rustc from path"),
+        )
+    } else if CRATES.iter().any(|c| c == &linked_crate) {
+        (linked_crate, untreated)
+    } else {
+        return None;
+    };
 
-        Some(LinkParts {
-            start: Start::Empty,
-            modules: None,
-            end: End::Module {
-                name: k.into(),
-                section: None,
-            },
-        })
+    if untreated.components().next().is_none() || untreated == Path::new(linked_crate) {
+        crate_only_link_parts(linked_crate, krate)
     } else {
         start_middle_end(untreated, krate)
     }
@@ -433,19 +1021,32 @@ fn associated_item_parts(path: &Path) -> Option<LinkParts<'_>> {
 
     lazy_static::lazy_static! {
         static ref ASSOC_ITEM: Regex = Regex::new(&format!(
-            r"^#(?P<dis>{})\.(?P<name>{})$",
-            crate::ITEM_TYPES.as_str(),
-            crate::RUST_IDENTIFIER,
+            r"^#(?P<dis>{ty})\.(?P<name>{rid})(?:\.field\.(?P<field>{rid}))?(?P<num>-\d+)?$",
+            ty = crate::ITEM_TYPES.as_str(),
+            rid = crate::RUST_IDENTIFIER,
         )).unwrap();
     }
 
     let captures = ASSOC_ITEM.captures(assoc_item.to_str()?)?;
-    let dis = Disambiguator::from(captures.name("dis")?.as_str());
+    let assoc_type = captures.name("dis")?.as_str();
+    let dis = Disambiguator::from(assoc_type);
     let name = captures.name("name")?.as_str();
+    let numbered = captures.name("num").is_some();
+    let field = captures.name("field").map(|x| x.as_str());
+
+    // Only variants have nested anchors for their fields.
+    if field.is_some() && assoc_type != "variant" {
+        return None;
+    }
 
     let start = Start::Local;
     let modules = None;
-    let end = End::Assoc(AssociatedItem { dis, name });
+    let end = End::Assoc(AssociatedItem {
+        dis,
+        name,
+        numbered,
+        field,
+    });
 
     Some(LinkParts {
         start,
@@ -487,7 +1088,7 @@ fn section_parts<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
 fn item_parts<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
     lazy_static::lazy_static! {
         static ref ITEM: Regex = Regex::new(&format!(
-            r"^(?P<i_ty>{ty})\.(?P<i_name>{rid})\.html(?:#(?P<ai_ty>{ty})\.(?P<ai_name>{rid})|(?P<section>{sec}))?$",
+            r"^(?P<i_ty>{ty})\.(?P<i_name>{rid})\.html(?:#(?P<ai_ty>{ty})\.(?P<ai_name>{rid})(?:\.field\.(?P<ai_field>{rid}))?(?P<ai_num>-\d+)?|(?P<section>{sec}))?$",
             ty = crate::ITEM_TYPES.as_str(),
             rid = crate::RUST_IDENTIFIER,
             sec = crate::HTML_SECTION,
@@ -502,6 +1103,7 @@ fn item_parts<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
 
     let assoc_type = captures.name("ai_ty").map(|x| x.as_str());
     let assoc_name = captures.name("ai_name").map(|x| x.as_str());
+    let assoc_field = captures.name("ai_field").map(|x| x.as_str());
     let section = captures.name("section").map(|x| {
         x.as_str()
             .strip_prefix('#')
@@ -513,9 +1115,16 @@ fn item_parts<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
     let added = if let Some(section) = section {
         Some(AssocOrSection::Section(Section { name: section }))
     } else if let (Some(assoc_type), Some(assoc_name)) = (assoc_type, assoc_name) {
+        // Only variants have nested anchors for their fields.
+        if assoc_field.is_some() && assoc_type != "variant" {
+            return None;
+        }
+
         Some(AssocOrSection::Assoc(AssociatedItem {
             dis: Disambiguator::from(assoc_type),
             name: assoc_name,
+            numbered: captures.name("ai_num").is_some(),
+            field: assoc_field,
         }))
     } else {
         None
@@ -583,7 +1192,7 @@ fn module_parts<'a>(path: &'a Path, krate: &Krate) -> Option<LinkParts<'a>> {
         // - ./index.html
         // - ../index.html
         // - path/to/mod/index.html
-        match path.parent().map(|p| p.components().next_back()).flatten() {
+        match path.parent().and_then(|p| p.components().next_back()) {
             // - index.html
             // - ./index.html
             Some(Component::CurDir) | None => End::Module {
@@ -663,7 +1272,7 @@ fn start_and_middle<'a>(untreated: &'a Path, end: End<'a>, krate: &Krate) -> Opt
                 if !crate::RUST_IDENTIFIER_RE.is_match(s) {
                     return None;
                 }
-                (Start::Mod(s), untreated.strip_prefix(os).expect("Stripping first module"))
+                (Start::Mod(s.into()), untreated.strip_prefix(os).expect("Stripping first module"))
             }
             Some(Component::Normal(_)) /* if supers > 0 */ => {
                 (Start::Supers(supers), untreated)
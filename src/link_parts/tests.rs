@@ -416,7 +416,7 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: None,
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
@@ -430,7 +430,7 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: None,
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
@@ -444,14 +444,16 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: None,
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
                 name: "Regex",
                 added: Some(AssocOrSection::Assoc(AssociatedItem {
                     dis: Disambiguator::Suffix("()"),
-                    name: "is_match"
+                    name: "is_match",
+                    numbered: false,
+                    field: None,
                 })),
             },
         })
@@ -461,7 +463,7 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: None,
             end: End::Module {
                 name: "bytes".into(),
@@ -474,7 +476,7 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: Some(Path::new("bytes")),
             end: End::Section(Section { name: "syntax" }),
         })
@@ -484,7 +486,7 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: Some(Path::new("bytes")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
@@ -499,14 +501,16 @@ fn test_favored_docs_rs() {
     assert_eq!(
         favored_docs_rs(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: Some(Path::new("bytes")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
                 name: "Regex",
                 added: Some(AssocOrSection::Assoc(AssociatedItem {
                     dis: Disambiguator::Suffix("()"),
-                    name: "is_match"
+                    name: "is_match",
+                    numbered: false,
+                    field: None,
                 })),
             },
         })
@@ -694,7 +698,7 @@ fn test_favored_doc_rust_lang_org() {
     assert_eq!(
         favored_doc_rust_lang_org(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("std"),
+            start: Start::Mod("std".into()),
             modules: None,
             end: End::Module {
                 name: "string".into(),
@@ -707,7 +711,7 @@ fn test_favored_doc_rust_lang_org() {
     assert_eq!(
         favored_doc_rust_lang_org(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("std"),
+            start: Start::Mod("std".into()),
             modules: Some(Path::new("string")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
@@ -722,7 +726,7 @@ fn test_favored_doc_rust_lang_org() {
     assert_eq!(
         favored_doc_rust_lang_org(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("std"),
+            start: Start::Mod("std".into()),
             modules: Some(Path::new("string")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
@@ -737,7 +741,7 @@ fn test_favored_doc_rust_lang_org() {
     assert_eq!(
         favored_doc_rust_lang_org(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("std"),
+            start: Start::Mod("std".into()),
             modules: Some(Path::new("string")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
@@ -745,6 +749,8 @@ fn test_favored_doc_rust_lang_org() {
                 added: Some(AssocOrSection::Assoc(AssociatedItem {
                     dis: Disambiguator::Suffix("()"),
                     name: "drain",
+                    numbered: false,
+                    field: None,
                 })),
             },
         }),
@@ -771,6 +777,8 @@ fn test_associated_item_parts() {
                 end: End::Assoc(AssociatedItem {
                     dis: Disambiguator::from(item),
                     name: "Item",
+                    numbered: false,
+                    field: None,
                 }),
             })
         );
@@ -785,6 +793,8 @@ fn test_associated_item_parts() {
             end: End::Assoc(AssociatedItem {
                 dis: Disambiguator::Prefix("type@"),
                 name: "Item",
+                numbered: false,
+                field: None,
             }),
         })
     );
@@ -798,12 +808,75 @@ fn test_associated_item_parts() {
             end: End::Assoc(AssociatedItem {
                 dis: Disambiguator::Prefix("type@"),
                 name: "Item",
+                numbered: false,
+                field: None,
+            }),
+        })
+    );
+
+    // Numbered anchors
+
+    let assoc_item = "#method.new-1";
+    assert_eq!(
+        associated_item_parts(Path::new(assoc_item)),
+        Some(LinkParts {
+            start: Start::Local,
+            modules: None,
+            end: End::Assoc(AssociatedItem {
+                dis: Disambiguator::Suffix("()"),
+                name: "new",
+                numbered: true,
+                field: None,
+            }),
+        })
+    );
+
+    let assoc_item = "./#associatedconstant.MAX-12";
+    assert_eq!(
+        associated_item_parts(Path::new(assoc_item)),
+        Some(LinkParts {
+            start: Start::Local,
+            modules: None,
+            end: End::Assoc(AssociatedItem {
+                dis: Disambiguator::Empty,
+                name: "MAX",
+                numbered: true,
+                field: None,
+            }),
+        })
+    );
+
+    // Variant fields
+
+    let assoc_item = "#variant.Foo.field.bar";
+    assert_eq!(
+        associated_item_parts(Path::new(assoc_item)),
+        Some(LinkParts {
+            start: Start::Local,
+            modules: None,
+            end: End::Assoc(AssociatedItem {
+                dis: Disambiguator::Empty,
+                name: "Foo",
+                numbered: false,
+                field: Some("bar"),
             }),
         })
     );
 
     // Failing items
 
+    let assoc_item = "#method.foo.field.bar";
+    assert_eq!(associated_item_parts(Path::new(assoc_item)), None);
+
+    let assoc_item = "#variant.Foo.field.";
+    assert_eq!(associated_item_parts(Path::new(assoc_item)), None);
+
+    let assoc_item = "#method.new-";
+    assert_eq!(associated_item_parts(Path::new(assoc_item)), None);
+
+    let assoc_item = "#method.new-a";
+    assert_eq!(associated_item_parts(Path::new(assoc_item)), None);
+
     let assoc_item = "struct.Item";
     assert_eq!(associated_item_parts(Path::new(assoc_item)), None);
 
@@ -858,6 +931,14 @@ fn test_section_parts() {
         None
     );
 
+    assert_eq!(
+        section_parts(
+            Path::new("#method.new-1"),
+            &crate::OPTS_KRATE_DIS_AND_FAV.krate
+        ),
+        None
+    );
+
     // Short sections
 
     assert_eq!(
@@ -990,7 +1071,7 @@ fn test_section_parts() {
             &crate::OPTS_KRATE_DIS_AND_FAV.krate
         ),
         Some(LinkParts {
-            start: Start::Mod("mod1"),
+            start: Start::Mod("mod1".into()),
             modules: None,
             end: End::Section(Section { name: "section" }),
         })
@@ -1002,7 +1083,7 @@ fn test_section_parts() {
             &crate::OPTS_KRATE_DIS_AND_FAV.krate
         ),
         Some(LinkParts {
-            start: Start::Mod("mod1"),
+            start: Start::Mod("mod1".into()),
             modules: Some(Path::new("mod2")),
             end: End::Section(Section { name: "section" }),
         })
@@ -1056,6 +1137,8 @@ fn test_item_parts() {
                     added: Some(AssocOrSection::Assoc(AssociatedItem {
                         dis: Disambiguator::Suffix("()"),
                         name: "call",
+                        numbered: false,
+                        field: None,
                     })),
                 }
             })
@@ -1131,6 +1214,55 @@ fn test_item_parts() {
         );
     }
 
+    assert_eq!(
+        item_parts(
+            Path::new("../struct.Type.html#method.new-2"),
+            &crate::OPTS_KRATE_DIS_AND_FAV.krate
+        ),
+        Some(LinkParts {
+            start: Start::Supers(1),
+            modules: None,
+            end: End::Item {
+                dis: Disambiguator::Prefix("type@"),
+                name: "Type",
+                added: Some(AssocOrSection::Assoc(AssociatedItem {
+                    dis: Disambiguator::Suffix("()"),
+                    name: "new",
+                    numbered: true,
+                    field: None,
+                })),
+            }
+        })
+    );
+
+    assert_eq!(
+        item_parts(
+            Path::new("enum.E.html#variant.Foo.field.bar"),
+            &crate::OPTS_KRATE_DIS_AND_FAV.krate
+        ),
+        Some(LinkParts {
+            start: Start::Empty,
+            modules: None,
+            end: End::Item {
+                dis: Disambiguator::Prefix("type@"),
+                name: "E",
+                added: Some(AssocOrSection::Assoc(AssociatedItem {
+                    dis: Disambiguator::Empty,
+                    name: "Foo",
+                    numbered: false,
+                    field: Some("bar"),
+                })),
+            }
+        })
+    );
+    assert_eq!(
+        item_parts(
+            Path::new("struct.S.html#structfield.foo.field.bar"),
+            &crate::OPTS_KRATE_DIS_AND_FAV.krate
+        ),
+        None
+    );
+
     assert_eq!(
         item_parts(Path::new("#section"), &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         None
@@ -1227,7 +1359,7 @@ fn test_module_parts() {
     assert_eq!(
         module_parts(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: None,
             end: End::Module {
                 name: "bytes".into(),
@@ -1240,7 +1372,7 @@ fn test_module_parts() {
     assert_eq!(
         module_parts(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: Some(Path::new("bytes")),
             end: End::Section(Section { name: "syntax" }),
         })
@@ -1324,7 +1456,7 @@ fn test_start_middle_end() {
     assert_eq!(
         start_middle_end(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: Some(Path::new("bytes")),
             end: End::Section(Section { name: "examples" }),
         })
@@ -1334,14 +1466,16 @@ fn test_start_middle_end() {
     assert_eq!(
         start_middle_end(link, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("std"),
+            start: Start::Mod("std".into()),
             modules: Some(Path::new("string")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
                 name: "String",
                 added: Some(AssocOrSection::Assoc(AssociatedItem {
                     dis: Disambiguator::Suffix("()"),
-                    name: "with_capacity"
+                    name: "with_capacity",
+                    numbered: false,
+                    field: None,
                 })),
             },
         })
@@ -1356,6 +1490,8 @@ fn test_start_middle_end() {
             end: End::Assoc(AssociatedItem {
                 dis: Disambiguator::Suffix("()"),
                 name: "with_capacity",
+                numbered: false,
+                field: None,
             }),
         })
     );
@@ -1381,7 +1517,7 @@ fn test_start_and_middle() {
     assert_eq!(
         start_and_middle(link, end, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("regex"),
+            start: Start::Mod("regex".into()),
             modules: Some(Path::new("bytes")),
             end: End::Section(Section { name: "examples" }),
         })
@@ -1394,19 +1530,23 @@ fn test_start_and_middle() {
         added: Some(AssocOrSection::Assoc(AssociatedItem {
             dis: Disambiguator::Suffix("()"),
             name: "with_capacity",
+            numbered: false,
+            field: None,
         })),
     };
     assert_eq!(
         start_and_middle(link, end, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
         Some(LinkParts {
-            start: Start::Mod("std"),
+            start: Start::Mod("std".into()),
             modules: Some(Path::new("string")),
             end: End::Item {
                 dis: Disambiguator::Prefix("type@"),
                 name: "String",
                 added: Some(AssocOrSection::Assoc(AssociatedItem {
                     dis: Disambiguator::Suffix("()"),
-                    name: "with_capacity"
+                    name: "with_capacity",
+                    numbered: false,
+                    field: None,
                 })),
             },
         })
@@ -1416,6 +1556,8 @@ fn test_start_and_middle() {
     let end = End::Assoc(AssociatedItem {
         dis: Disambiguator::Suffix("()"),
         name: "with_capacity",
+        numbered: false,
+        field: None,
     });
     assert_eq!(
         start_and_middle(link, end, &crate::OPTS_KRATE_DIS_AND_FAV.krate),
@@ -1425,6 +1567,8 @@ fn test_start_and_middle() {
             end: End::Assoc(AssociatedItem {
                 dis: Disambiguator::Suffix("()"),
                 name: "with_capacity",
+                numbered: false,
+                field: None,
             }),
         })
     );
@@ -1479,18 +1623,24 @@ fn link_parts_dis() {
     lp.end = End::Assoc(AssociatedItem {
         dis: Empty,
         name: "item",
+        numbered: false,
+        field: None,
     });
     assert_eq!(Empty, lp.dis());
 
     lp.end = End::Assoc(AssociatedItem {
         dis: Prefix("type@"),
         name: "item",
+        numbered: false,
+        field: None,
     });
     assert_eq!(Prefix("type@"), lp.dis());
 
     lp.end = End::Assoc(AssociatedItem {
         dis: Suffix("()"),
         name: "item",
+        numbered: false,
+        field: None,
     });
     assert_eq!(Suffix("()"), lp.dis());
 
@@ -1502,6 +1652,8 @@ fn link_parts_dis() {
         added: Some(AssocOrSection::Assoc(AssociatedItem {
             dis: Empty,
             name: "item",
+            numbered: false,
+            field: None,
         })),
     };
     assert_eq!(Empty, lp.dis());
@@ -1512,6 +1664,8 @@ fn link_parts_dis() {
         added: Some(AssocOrSection::Assoc(AssociatedItem {
             dis: Prefix("type@"),
             name: "item",
+            numbered: false,
+            field: None,
         })),
     };
     assert_eq!(Prefix("type@"), lp.dis());
@@ -1522,6 +1676,8 @@ fn link_parts_dis() {
         added: Some(AssocOrSection::Assoc(AssociatedItem {
             dis: Suffix("()"),
             name: "item",
+            numbered: false,
+            field: None,
         })),
     };
     assert_eq!(Suffix("()"), lp.dis());
@@ -1729,7 +1885,7 @@ fn test_link_parts_transform() {
 
     fn check_transform(value: &str, target: &str, ctx: &ConversionContext) {
         let parts = link_parts(Path::new(value), ctx.options()).unwrap();
-        let transform = parts.clone().transform(&ctx);
+        let transform = parts.clone().transform(ctx);
         assert_eq!(
             target, transform,
             "\n--> Value: {:#?}, parts: {:#?}",
@@ -1750,6 +1906,9 @@ fn test_link_parts_transform() {
     check_transform("#method.drain", "Self::drain()", &ctx_dis);
     check_transform("#method.drain", "Self::drain()", &ctx_no_dis);
 
+    check_transform("#variant.Foo", "Self::Foo", &ctx_dis);
+    check_transform("#variant.Foo.field.bar", "Self::Foo::bar", &ctx_no_dis);
+
     ctx_dis.set_current_type_block("Block".into());
     ctx_no_dis.set_current_type_block("Block".into());
 
@@ -2343,6 +2502,28 @@ const TEST_TRANSFORM_VALUES: &[(&str, &str, &str)] = &[
         "super::mod1::mod2::Type",
         "super::mod1::mod2::Type",
     ),
+    ("#method.new-1", "Block::new()", "Block::new()"),
+    ("#variant.Foo", "Block::Foo", "Block::Foo"),
+    (
+        "#variant.Foo.field.bar",
+        "Block::Foo::bar",
+        "Block::Foo::bar",
+    ),
+    (
+        "enum.E.html#variant.Foo.field.bar",
+        "E::Foo::bar",
+        "E::Foo::bar",
+    ),
+    (
+        "../mod1/enum.E.html#variant.Foo.field.bar",
+        "super::mod1::E::Foo::bar",
+        "super::mod1::E::Foo::bar",
+    ),
+    (
+        "struct.Type.html#method.new-1",
+        "Type::new()",
+        "Type::new()",
+    ),
     ("regex", "mod@regex", "regex"),
     (
         "../../regex",
@@ -2368,3 +2549,587 @@ const TEST_TRANSFORM_VALUES: &[(&str, &str, &str)] = &[
         "regex::bytes#syntax",
     ),
 ];
+
+/// Checks that `link` is transformed to `target` with `opts`, once `parse`
+/// has found its parts.
+fn check_parts<'a>(
+    parse: impl FnOnce(&'a Path, &ConversionOptions) -> Option<LinkParts<'a>>,
+    opts: &ConversionOptions,
+    link: &'a str,
+    target: Option<&str>,
+) {
+    let ctx = crate::ConversionContext::with_options(opts.clone());
+    let parts = parse(Path::new(link), opts);
+    assert_eq!(
+        parts.map(|p| p.transform(&ctx)).as_deref(),
+        target,
+        "{}",
+        link
+    );
+}
+
+#[test]
+fn test_favored_host() {
+    let config: crate::RawFileConfig = toml::from_str(
+        r#"
+        [[favored]]
+        host = "docs.internal.example"
+        pattern = "{crate}/{version}"
+
+        [[favored]]
+        host = "mirror.example/rust"
+        pattern = "{channel}"
+        "#,
+    )
+    .unwrap();
+    let config = config.finish(Path::new(".")).unwrap();
+    let opts = ConversionOptions {
+        ignored_links: &config,
+        ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+    };
+    let check = |link: &str, target: Option<&str>| check_parts(favored_parts, &opts, link, target);
+
+    check("https://docs.internal.example/regex", Some("regex"));
+    check("https://docs.internal.example/regex/1.4.2/", Some("regex"));
+    check("https://docs.internal.example/krate/1.0.0", Some("crate"));
+    check(
+        "https://docs.internal.example/regex/1.4.2/regex/struct.Regex.html",
+        Some("regex::Regex"),
+    );
+    check(
+        "https://docs.internal.example/krate-name/1.0.0/krate/bytes/index.html#syntax",
+        Some("crate::bytes#syntax"),
+    );
+    check(
+        "https://mirror.example/rust/nightly/std/option/enum.Option.html",
+        Some("std::option::Option"),
+    );
+    check("https://mirror.example/rust/nightly/std", Some("std"));
+
+    // Wrong host path or unknown host.
+    check("https://mirror.example/other/nightly/std/index.html", None);
+    check("https://mirror.example/rust", None);
+    check(
+        "https://other.example/regex/1.4.2/regex/struct.Regex.html",
+        None,
+    );
+
+    // The standard crates are always available.
+    let deps = [crate::Dependency {
+        lib: "regex".into(),
+        name: "regex".into(),
+        version: None,
+    }];
+    let with_deps = ConversionOptions {
+        dependencies: Some(&deps),
+        ..opts.clone()
+    };
+    let check =
+        |link: &str, target: Option<&str>| check_parts(favored_parts, &with_deps, link, target);
+    check(
+        "https://mirror.example/rust/nightly/std/option/enum.Option.html",
+        Some("std::option::Option"),
+    );
+    check("https://mirror.example/rust/nightly/core", Some("core"));
+    check(
+        "https://docs.internal.example/regex/1.4.2/regex/struct.Regex.html",
+        Some("regex::Regex"),
+    );
+    check(
+        "https://docs.internal.example/tokio/0.3.5/tokio/fs/index.html",
+        None,
+    );
+
+    // Favored links are disabled.
+    let opts = ConversionOptions {
+        favored_links: false,
+        ..opts
+    };
+    assert_eq!(
+        favored_parts(
+            Path::new("https://docs.internal.example/regex/1.4.2/regex/struct.Regex.html"),
+            &opts
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_favored_html_root() {
+    let opts = ConversionOptions {
+        html_root_url: Some("https://docs.example/krate-docs/1.0"),
+        ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+    };
+    let check = |link: &str, target: Option<&str>| check_parts(favored_parts, &opts, link, target);
+
+    check("https://docs.example/krate-docs/1.0", Some("crate"));
+    check("https://docs.example/krate-docs/1.0/", Some("crate"));
+    check("https://docs.example/krate-docs/1.0/krate", Some("crate"));
+    check(
+        "https://docs.example/krate-docs/1.0/krate/mod1/struct.Type.html#method.new",
+        Some("crate::mod1::Type::new()"),
+    );
+    check("https://docs.example/krate-docs/0.9/krate/index.html", None);
+    check("https://docs.example/other/struct.Type.html", None);
+
+    // docs.rs is still handled when it is not the root.
+    check(
+        "https://docs.rs/regex/1.4.2/regex/struct.Regex.html",
+        Some("regex::Regex"),
+    );
+}
+
+#[test]
+fn test_favored_doc_roots() {
+    let doc_roots = [
+        crate::DocRoot {
+            url: "https://docs.example/dep-docs".into(),
+            krate: "dep".into(),
+        },
+        crate::DocRoot {
+            url: "https://other.example/krate".into(),
+            krate: "krate".into(),
+        },
+    ];
+    let opts = ConversionOptions {
+        doc_roots: &doc_roots,
+        ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+    };
+    let check = |link: &str, target: Option<&str>| check_parts(favored_parts, &opts, link, target);
+
+    check("https://docs.example/dep-docs", Some("dep"));
+    check("https://docs.example/dep-docs/dep/", Some("dep"));
+    check(
+        "https://docs.example/dep-docs/dep/mod1/struct.Type.html#method.new",
+        Some("dep::mod1::Type::new()"),
+    );
+    check("https://other.example/krate", Some("crate"));
+    check(
+        "https://other.example/krate/krate/fn.func.html",
+        Some("crate::func()"),
+    );
+    check("https://docs.example/other-docs/dep/index.html", None);
+}
+
+#[test]
+fn test_favored_dependencies() {
+    let deps = [
+        crate::Dependency {
+            lib: "regex".into(),
+            name: "regex".into(),
+            version: None,
+        },
+        crate::Dependency {
+            lib: "actix_web".into(),
+            name: "web".into(),
+            version: None,
+        },
+    ];
+    let opts = ConversionOptions {
+        dependencies: Some(&deps),
+        ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+    };
+    let check = |link: &str, target: Option<&str>| check_parts(favored_parts, &opts, link, target);
+
+    check(
+        "https://docs.rs/regex/1.4.2/regex/struct.Regex.html",
+        Some("regex::Regex"),
+    );
+    check("https://docs.rs/regex", Some("regex"));
+    check(
+        "https://docs.rs/regex/1.4.2/regex/bytes/index.html",
+        Some("regex::bytes"),
+    );
+    check(
+        "https://docs.rs/actix-web/3.3.2/actix_web/struct.App.html",
+        Some("web::App"),
+    );
+    check("https://docs.rs/actix-web/3.3.2/actix_web/", Some("web"));
+    check(
+        "https://docs.rs/krate/1.0.0/krate/struct.Type.html",
+        Some("crate::Type"),
+    );
+
+    // Not a dependency.
+    check("https://docs.rs/tokio/0.3.5/tokio/fs/index.html", None);
+    check("https://docs.rs/tokio", None);
+
+    // The standard crates are always available.
+    check(
+        "https://doc.rust-lang.org/std/option/enum.Option.html",
+        Some("std::option::Option"),
+    );
+}
+
+#[test]
+fn test_is_compatible() {
+    let check =
+        |a: &str, b: &str| is_compatible(&Version::parse(a).unwrap(), &Version::parse(b).unwrap());
+
+    assert!(check("1.0.0", "1.4.2"));
+    assert!(check("0.7.0", "0.7.3"));
+    assert!(check("0.0.3", "0.0.3"));
+
+    assert!(!check("0.2.11", "1.4.2"));
+    assert!(!check("1.0.0", "2.0.0"));
+    assert!(!check("0.7.3", "0.8.0"));
+    assert!(!check("0.0.3", "0.0.4"));
+}
+
+#[test]
+fn test_docs_rs_shapes() {
+    let check = |link: &str, target: Option<&str>| {
+        check_parts(
+            |path, opts| favored_docs_rs(path, &opts.krate),
+            &crate::OPTS_KRATE_NO_DIS_BUT_FAV,
+            link,
+            target,
+        )
+    };
+
+    for version in &[
+        "1.4.2",
+        "latest",
+        "*",
+        "^1.2",
+        "%5E1.2",
+        "~1",
+        "%7E1",
+        "=1.4.2",
+        ">=1.0",
+        "1.2",
+        "1",
+        "1.0.0-alpha.3",
+        "1.0.0+build",
+        "1.0.0-beta.1+build.5",
+    ] {
+        check(
+            &format!("https://docs.rs/regex/{}/regex/struct.Regex.html", version),
+            Some("regex::Regex"),
+        );
+        check(&format!("https://docs.rs/regex/{}", version), Some("regex"));
+        check(
+            &format!(
+                "https://docs.rs/regex/{}/x86_64-unknown-linux-gnu/regex/struct.Regex.html",
+                version
+            ),
+            Some("regex::Regex"),
+        );
+    }
+
+    check(
+        "https://docs.rs/winapi/0.3.9/x86_64-pc-windows-msvc/winapi/um/index.html",
+        Some("winapi::um"),
+    );
+    check(
+        "https://docs.rs/libc/latest/wasm32-unknown-unknown/libc/fn.abs.html",
+        Some("libc::abs()"),
+    );
+    check(
+        "https://docs.rs/regex/1.4.2/x86_64-unknown-linux-gnu",
+        Some("regex"),
+    );
+
+    check("https://docs.rs/tracing-serde/badge.svg", None);
+    check("https://docs.rs/regex/1.x/regex/struct.Regex.html", None);
+    check("https://docs.rs/regex/v1.4.2/regex/struct.Regex.html", None);
+}
+
+#[test]
+fn test_docs_rs_version() {
+    let locked = Version::parse("1.4.2").unwrap();
+    let check = |version: &str| {
+        DocsRsVersion::new(version)
+            .unwrap()
+            .is_compatible_with(&locked)
+    };
+
+    assert!(check("latest"));
+    assert!(check("*"));
+    assert!(check("1.0.0"));
+    assert!(check("1.5.0-alpha.1"));
+    assert!(check("^1.2"));
+    assert!(check("~1.3"));
+    assert!(check("~1"));
+    assert!(check(">=0.2"));
+    assert!(check("<2"));
+    assert!(check("1"));
+
+    assert!(!check("0.2.11"));
+    assert!(!check("2.0.0-rc.1"));
+    assert!(!check("^0.2"));
+    assert!(!check("~2.1"));
+    assert!(!check("=0.2.11"));
+    assert!(!check("%5E0.2"));
+}
+
+#[test]
+fn test_favored_nightly_rustc() {
+    let check = |krate: &str, link: &str, target: Option<&str>| {
+        let opts = ConversionOptions {
+            krate: Krate::new(krate).unwrap(),
+            ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+        };
+        check_parts(favored_parts, &opts, link, target);
+    };
+
+    check(
+        "krate",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.TyCtxt.html",
+        Some("rustc_middle::ty::TyCtxt"),
+    );
+    check(
+        "krate",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.TyCtxt.html#method.mk_ty",
+        Some("rustc_middle::ty::TyCtxt::mk_ty()"),
+    );
+    check(
+        "krate",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/rustc_lint/index.html",
+        Some("rustc_lint"),
+    );
+    check(
+        "krate",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/rustc_lint",
+        Some("rustc_lint"),
+    );
+    check(
+        "rustc_middle",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.TyCtxt.html",
+        Some("crate::ty::TyCtxt"),
+    );
+    check(
+        "rustc_middle",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/",
+        Some("crate"),
+    );
+
+    check(
+        "krate",
+        "https://doc.rust-lang.org/nightly/nightly-rustc",
+        None,
+    );
+    check(
+        "krate",
+        "https://doc.rust-lang.org/nightly/nightly-rustc/search-index.js",
+        None,
+    );
+}
+
+#[test]
+fn test_with_facade() {
+    let check = |krate: &str, facade: Facade, link: &str, target: Result<&str, ()>| {
+        let opts = ConversionOptions {
+            krate: Krate::new(krate).unwrap(),
+            facade,
+            ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+        };
+        let ctx = crate::ConversionContext::with_options(opts.clone());
+        let parts = link_parts(Path::new(link), &opts).unwrap();
+        assert_eq!(
+            parts
+                .with_facade(&opts)
+                .map(|p| p.transform(&ctx))
+                .map_err(|_| ()),
+            target.map(String::from),
+            "{} -- {:?} -- {}",
+            krate,
+            facade,
+            link
+        );
+    };
+
+    let option = "https://doc.rust-lang.org/std/option/enum.Option.html";
+    check("krate", Facade::Std, option, Ok("std::option::Option"));
+    check("krate", Facade::Alloc, option, Ok("core::option::Option"));
+    check("krate", Facade::Core, option, Ok("core::option::Option"));
+
+    let vec = "https://doc.rust-lang.org/nightly/std/vec/struct.Vec.html#method.push";
+    check("krate", Facade::Alloc, vec, Ok("alloc::vec::Vec::push()"));
+    check("krate", Facade::Core, vec, Err(()));
+
+    let alloc_vec = "https://doc.rust-lang.org/alloc/vec/struct.Vec.html";
+    check("krate", Facade::Alloc, alloc_vec, Ok("alloc::vec::Vec"));
+    check("krate", Facade::Core, alloc_vec, Err(()));
+
+    let macro_vec = "https://doc.rust-lang.org/std/macro.vec.html";
+    check("krate", Facade::Alloc, macro_vec, Ok("alloc::vec!"));
+
+    let hash_map = "https://doc.rust-lang.org/std/collections/struct.HashMap.html";
+    check("krate", Facade::Alloc, hash_map, Err(()));
+    check(
+        "krate",
+        Facade::Alloc,
+        "https://doc.rust-lang.org/std/io/index.html",
+        Err(()),
+    );
+    check(
+        "krate",
+        Facade::Alloc,
+        "https://doc.rust-lang.org/std",
+        Err(()),
+    );
+    check(
+        "krate",
+        Facade::Alloc,
+        "https://doc.rust-lang.org/std/collections/index.html",
+        Ok("alloc::collections"),
+    );
+
+    // Relative links inside the standard crates.
+    let relative = "../../std/option/enum.Option.html";
+    check("core", Facade::Core, relative, Ok("crate::option::Option"));
+    check("alloc", Facade::Alloc, relative, Ok("core::option::Option"));
+    check(
+        "alloc",
+        Facade::Alloc,
+        "../../std/string/struct.String.html",
+        Ok("crate::string::String"),
+    );
+    check(
+        "core",
+        Facade::Core,
+        "../../std/string/struct.String.html",
+        Err(()),
+    );
+    check("krate", Facade::Std, relative, Ok("std::option::Option"));
+
+    // Links to other crates are not changed.
+    check(
+        "krate",
+        Facade::Core,
+        "../mod1/struct.Type.html",
+        Ok("super::mod1::Type"),
+    );
+
+    // Sibling modules with the name of a standard crate.
+    let check_in_module = |link: &str, target: Option<&str>| {
+        let deps = [];
+        let opts = ConversionOptions {
+            module_depth: 1,
+            dependencies: Some(&deps),
+            ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+        };
+        check_parts(
+            |path, opts| link_parts(path, opts).ok(),
+            &opts,
+            link,
+            target,
+        );
+    };
+    check_in_module("../alloc/struct.Foo.html", Some("super::alloc::Foo"));
+    check_in_module("../core/fn.f.html", Some("super::core::f()"));
+    check_in_module("../std/index.html", Some("super::std"));
+    check_in_module(
+        "../../std/option/enum.Option.html",
+        Some("std::option::Option"),
+    );
+    check_in_module("../../core/index.html", Some("core"));
+    check(
+        "krate",
+        Facade::Core,
+        "https://docs.rs/regex/1.4.2/regex/struct.Regex.html",
+        Ok("regex::Regex"),
+    );
+}
+
+#[test]
+fn test_workspace_crates() {
+    let workspace_crates = ["krate".to_string(), "b".to_string(), "c".to_string()];
+    let deps = [crate::Dependency {
+        lib: "b".into(),
+        name: "b".into(),
+        version: None,
+    }];
+
+    let check = |dependencies: Option<&[crate::Dependency]>, link: &str, target: Option<&str>| {
+        let opts = ConversionOptions {
+            workspace_crates: &workspace_crates,
+            dependencies,
+            ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+        };
+        check_parts(
+            |path, opts| link_parts(path, opts).ok(),
+            &opts,
+            link,
+            target,
+        );
+    };
+
+    check(None, "../b/struct.Client.html", Some("b::Client"));
+    check(
+        None,
+        "../../b/net/struct.Client.html#method.new",
+        Some("b::net::Client::new()"),
+    );
+    check(None, "../b/index.html", Some("b"));
+    check(None, "../b", Some("b"));
+    check(None, "../b/net/index.html", Some("b::net"));
+    check(None, "../c/fn.run.html", Some("c::run()"));
+    check(None, "../krate/struct.Type.html", Some("crate::Type"));
+
+    // Not a crate of the workspace.
+    check(None, "../d/struct.Client.html", Some("super::d::Client"));
+    // Not leaving the current directory.
+    check(None, "b/struct.Client.html", Some("b::Client"));
+    // Not leaving the documentation of the crate.
+    let opts = ConversionOptions {
+        workspace_crates: &workspace_crates,
+        module_depth: 1,
+        ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+    };
+    check_parts(
+        |path, opts| link_parts(path, opts).ok(),
+        &opts,
+        "../b/struct.Client.html",
+        Some("super::b::Client"),
+    );
+
+    // Only dependencies are transformed when they are known.
+    check(Some(&deps), "../b/struct.Client.html", Some("b::Client"));
+    check(Some(&deps), "../c/fn.run.html", None);
+}
+
+#[test]
+fn test_own_lib() {
+    // A binary named like the library of its package.
+    let deps = [crate::Dependency {
+        lib: "krate".into(),
+        name: "krate".into(),
+        version: Some(cargo_metadata::Version::new(1, 0, 0)),
+    }];
+    let opts = ConversionOptions {
+        dependencies: Some(&deps),
+        ..crate::OPTS_KRATE_NO_DIS_BUT_FAV.clone()
+    };
+    let check = |link: &str, target: Option<&str>| {
+        check_parts(
+            |path, opts| link_parts(path, opts).ok(),
+            &opts,
+            link,
+            target,
+        )
+    };
+
+    check(
+        "https://docs.rs/krate/1.0.0/krate/struct.Type.html",
+        Some("krate::Type"),
+    );
+    check(
+        "https://docs.rs/krate/1.0.0/krate/bytes/index.html",
+        Some("krate::bytes"),
+    );
+    check("https://docs.rs/krate", Some("krate"));
+    check("../krate/struct.Type.html", Some("krate::Type"));
+    // Links inside the binary are unchanged.
+    check("struct.Type.html", Some("Type"));
+    check("../struct.Type.html", Some("super::Type"));
+
+    // In the library itself.
+    check_parts(
+        |path, opts| link_parts(path, opts).ok(),
+        &crate::OPTS_KRATE_NO_DIS_BUT_FAV,
+        "https://docs.rs/krate/1.0.0/krate/struct.Type.html",
+        Some("crate::Type"),
+    );
+}
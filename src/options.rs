@@ -30,8 +30,39 @@ pub struct ConversionOptions<'cf> {
     /// before being saved here.
     pub ignored_links: &'cf crate::FileConfig,
 
-    ///
+    /// Path of the file being converted.
     pub current_path: &'cf Path,
+
+    /// Directory of the locally generated documentation for the module of
+    /// the current file. When present, `#section` fragments are checked
+    /// against it.
+    pub doc_dir: Option<&'cf Path>,
+
+    /// Value of the `#![doc(html_root_url = "...")]` attribute of the crate,
+    /// if any. Links under it are links into the current crate.
+    pub html_root_url: Option<&'cf str>,
+
+    /// Documentation roots declared by the dependencies with their own
+    /// `html_root_url` attribute.
+    pub doc_roots: &'cf [DocRoot],
+
+    /// Crates available when building the documentation of the current
+    /// crate. When known, favored links to other crates are only transformed
+    /// if they are part of it.
+    pub dependencies: Option<&'cf [Dependency]>,
+
+    /// Most complete standard crate available to the current crate: links
+    /// into `std` are rewritten to `core` or `alloc` when it is not `std`.
+    pub facade: crate::Facade,
+
+    /// Names of the crates of the workspace. Relative links to their
+    /// documentation, like `../b/struct.Client.html`, are links to them.
+    pub workspace_crates: &'cf [String],
+
+    /// Number of modules between the root of the crate and the module of the
+    /// current file, like 2 for `src/a/b.rs`. Relative links going up more
+    /// than this leave the documentation of the crate.
+    pub module_depth: usize,
 }
 
 impl<'cf> ConversionOptions<'cf> {
@@ -39,6 +70,46 @@ impl<'cf> ConversionOptions<'cf> {
         self.ignored_links
             .is_ignored(self.current_path, name, value)
     }
+
+    /// How to treat `#impl-...` and `#deref-methods-...` anchors.
+    pub fn impl_anchors(&self) -> crate::ImplAnchors {
+        self.ignored_links.impl_anchors()
+    }
+
+    /// What to do with `docs.rs` links to an incompatible version.
+    pub fn version_mismatch(&self) -> crate::VersionMismatch {
+        self.ignored_links.version_mismatch()
+    }
+
+    /// Additional hosts whose links are favored, from the configuration file.
+    pub fn favored_hosts(&self) -> &[crate::FavoredHost] {
+        self.ignored_links.favored_hosts()
+    }
+}
+
+/// Root of the documentation of a dependency, read from its
+/// `#![doc(html_root_url = "...")]` attribute.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DocRoot {
+    /// URL of the root, never ending with a `/`.
+    pub url: String,
+
+    /// Name of the library of the dependency, as in its documentation.
+    pub krate: String,
+}
+
+/// A dependency that can be linked to in the documentation.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Dependency {
+    /// Name of the library of the dependency, as in its documentation.
+    pub lib: String,
+
+    /// Name of the dependency in Rust code, which is different from `lib`
+    /// when it was renamed with `package = "..."`.
+    pub name: String,
+
+    /// Version of the dependency in `Cargo.lock`, when it is resolved.
+    pub version: Option<cargo_metadata::Version>,
 }
 
 /// A valid Rust identifier for a crate.
@@ -0,0 +1,209 @@
+//! Checks `#section` fragments against the documentation generated locally
+//! by `rustdoc`.
+//!
+//! `rustdoc` derives the ids of headings from their text so renaming a
+//! heading silently breaks the links pointing to it. Reading the ids from the
+//! generated HTML pages means the slug rules of `rustdoc` are applied for us.
+use regex::Regex;
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+/// Returns a warning when `link` has a fragment that does not exist in the
+/// page it points to.
+///
+/// `doc_dir` is the directory holding the documentation of the module the
+/// link is written in and `type_block` is the type whose page holds local
+/// fragments like `#examples`, if any (the module page is used otherwise).
+///
+/// Nothing is reported when the page cannot be read: the documentation may
+/// simply not have been generated. Links to a website (favored links like
+/// `https://docs.rs/...`) are not checked either, only relative links have a
+/// page in `doc_dir`.
+pub fn check_fragment(doc_dir: &Path, link: &Path, type_block: Option<&str>) -> Option<String> {
+    if link.starts_with("http:") || link.starts_with("https:") {
+        return None;
+    }
+
+    let link = link.to_str()?;
+    let (page, fragment) = link.split_at(link.find('#')?);
+    let fragment = &fragment[1..];
+
+    let page = page_path(doc_dir, Path::new(page), type_block)?;
+    let ids = page_ids(&page)?;
+
+    if ids.contains(fragment) {
+        None
+    } else {
+        Some(format!(
+            "Fragment '#{}' not found in '{}'",
+            fragment,
+            page.display()
+        ))
+    }
+}
+
+/// Finds the HTML page a link (without its fragment) points to.
+fn page_path(doc_dir: &Path, page: &Path, type_block: Option<&str>) -> Option<PathBuf> {
+    let is_local = page.components().all(|c| matches!(c, Component::CurDir));
+
+    if is_local {
+        let ty = match type_block {
+            Some(ty) => ty,
+            None => return Some(doc_dir.join("index.html")),
+        };
+
+        // The kind of the type is unknown, so every page with the right name
+        // is a candidate.
+        return std::fs::read_dir(doc_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.file_name()
+                    .and_then(|f| f.to_str())
+                    .and_then(|f| f.strip_suffix(".html"))
+                    .and_then(|f| f.split('.').nth(1))
+                    == Some(ty)
+            });
+    }
+
+    let page = doc_dir.join(page);
+    if page.extension().is_some_and(|ext| ext == "html") {
+        Some(page)
+    } else {
+        Some(page.join("index.html"))
+    }
+}
+
+/// Reads all the `id` attributes of the given HTML page.
+fn page_ids(page: &Path) -> Option<HashSet<String>> {
+    lazy_static::lazy_static! {
+        static ref ID: Regex = Regex::new(r#"\sid="(?P<id>[^"]+)""#).unwrap();
+    }
+
+    let content = std::fs::read_to_string(page).ok()?;
+    Some(
+        ID.captures_iter(&content)
+            .map(|c| c["id"].to_string())
+            .collect(),
+    )
+}
+
+/// Directory holding the documentation of the module reached through
+/// `modules` from the root of the crate, see `module_path`.
+pub fn module_doc_dir(doc_root: &Path, krate: &str, modules: &[&OsStr]) -> PathBuf {
+    let mut dir = doc_root.join(krate);
+    dir.extend(modules);
+    dir
+}
+
+/// Modules leading from the root of the crate to the module defined in
+/// `file`.
+///
+/// `file` is either relative to the `src` directory of the crate or has a
+/// `src` component, the module path starting after the last one. It must
+/// not be the root file of the crate, which defines no module whatever its
+/// name, like `src/bin/tool.rs`.
+pub fn module_path(file: &Path) -> Vec<&OsStr> {
+    let comps: Vec<_> = file.components().collect();
+    let start = comps
+        .iter()
+        .rposition(|c| c == &Component::Normal("src".as_ref()))
+        .map_or(0, |pos| pos + 1);
+
+    let end = comps.len().saturating_sub(1);
+    let mut modules: Vec<_> = comps
+        .iter()
+        .take(end)
+        .skip(start)
+        .filter_map(|c| match c {
+            Component::Normal(m) => Some(*m),
+            _ => None,
+        })
+        .collect();
+
+    match file.file_stem() {
+        Some(stem) if !["lib", "main", "mod"].iter().any(|&s| stem == s) => modules.push(stem),
+        _ => (),
+    }
+
+    modules
+}
+
+#[test]
+fn test_module_doc_dir() {
+    let root = Path::new("target/doc");
+
+    let check = |file: &str, expected: &str| {
+        assert_eq!(
+            module_doc_dir(root, "krate", &module_path(Path::new(file))),
+            root.join(expected)
+        );
+    };
+
+    check("lib.rs", "krate");
+    check("main.rs", "krate");
+    check("mod1.rs", "krate/mod1");
+    check("mod1/mod.rs", "krate/mod1");
+    check("mod1/mod2.rs", "krate/mod1/mod2");
+    check("path/to/src/lib.rs", "krate");
+    check("/path/to/src/mod1/mod2.rs", "krate/mod1/mod2");
+
+    assert_eq!(module_path(Path::new("src/lib.rs")).len(), 0);
+    assert_eq!(module_path(Path::new("mod1/mod.rs")).len(), 1);
+    assert_eq!(module_path(Path::new("src/mod1/mod2.rs")).len(), 2);
+}
+
+#[test]
+fn test_check_fragment() {
+    let fixture = crate::fixture(&[
+        (
+            "krate/mod1/struct.Type.html",
+            r#"<h1 id="examples">Examples</h1><h2 id="method.new" class="method">"#,
+        ),
+        (
+            "krate/mod1/index.html",
+            r#"<h1 id="module-docs">Module docs</h1>"#,
+        ),
+    ]);
+    let mod_dir = fixture.path().join("krate").join("mod1");
+
+    let check = |link: &str, type_block: Option<&str>| {
+        check_fragment(&mod_dir, Path::new(link), type_block)
+    };
+
+    assert_eq!(check("struct.Type.html#examples", None), None);
+    assert_eq!(check("./struct.Type.html#method.new", None), None);
+    assert_eq!(check("#examples", Some("Type")), None);
+    assert_eq!(check("#module-docs", None), None);
+    assert_eq!(check("../mod1#module-docs", None), None);
+    assert_eq!(check("../mod1/index.html#module-docs", None), None);
+
+    assert!(check("struct.Type.html#example", None).is_some());
+    assert!(check("#examples", None).is_some());
+    assert!(check("../mod1#examples", None).is_some());
+
+    // Missing pages and links without fragments are never reported.
+    assert_eq!(check("struct.Missing.html#examples", None), None);
+    assert_eq!(check("#examples", Some("Missing")), None);
+    assert_eq!(check("struct.Type.html", None), None);
+
+    // Links to websites have no local page, even when a relative page with
+    // the same path exists.
+    std::fs::create_dir_all(mod_dir.join("https:")).unwrap();
+    std::fs::copy(
+        mod_dir.join("struct.Type.html"),
+        mod_dir.join("https:").join("struct.Type.html"),
+    )
+    .unwrap();
+    assert_eq!(check("https:/struct.Type.html#example", None), None);
+    assert_eq!(
+        check(
+            "https://docs.rs/krate/1.0.0/krate/mod1/struct.Type.html#example",
+            None
+        ),
+        None
+    );
+}
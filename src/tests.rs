@@ -0,0 +1,488 @@
+use super::*;
+use argh::FromArgs;
+use std::path::PathBuf;
+
+/// Arguments of a run with the `extra` flags.
+fn args(extra: &[&str]) -> CliArgs {
+    CliArgs::from_args(&["cargo-intraconv"], extra).expect("Valid arguments")
+}
+
+/// Configuration of a run started in `start_dir`, without dependencies.
+fn run_config<'a>(
+    args: &'a CliArgs,
+    file_config: &'a FileConfig,
+    start_dir: &'a Path,
+) -> RunConfig<'a> {
+    lazy_static::lazy_static! {
+        static ref NO_DEPS: WorkspaceDeps = Default::default();
+    }
+
+    RunConfig {
+        args,
+        file_config,
+        doc_root: None,
+        deps: &NO_DEPS,
+        default_crate: "krate",
+        start_dir,
+        repo: None,
+        changed_lines: None,
+        out_dir: None,
+    }
+}
+
+fn crate_info() -> CrateInfo<'static> {
+    CrateInfo {
+        krate: "krate".into(),
+        ..Default::default()
+    }
+}
+
+const LIB_RS: &str = "//! [`Vec`](struct.Vec.html)\n//! [`Box`](struct.Box.html)\n";
+
+#[test]
+fn test_display_relative_to_start_dir() {
+    let fixture = crate::fixture(&[("src/lib.rs", LIB_RS)]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let file = dir.join("src/lib.rs");
+    let cwd = env::current_dir().unwrap();
+
+    let args = args(&[]);
+    let output = run_for_file(&file, &run_config(&args, &NO_IGNORE, &dir), &crate_info())
+        .unwrap()
+        .output;
+    assert!(output.starts_with("krate: src/lib.rs\n"), "{}", output);
+
+    // Files outside of the starting directory are displayed as given.
+    let elsewhere = dir.join("elsewhere");
+    let output = run_for_file(
+        &file,
+        &run_config(&args, &NO_IGNORE, &elsewhere),
+        &crate_info(),
+    )
+    .unwrap()
+    .output;
+    assert!(
+        output.starts_with(&format!("krate: {}\n", file.display())),
+        "{}",
+        output
+    );
+
+    // Converting files never changes the current directory.
+    assert_eq!(env::current_dir().unwrap(), cwd);
+}
+
+#[test]
+fn test_relative_per_file_ignores() {
+    let fixture = crate::fixture(&[("src/lib.rs", LIB_RS), ("src/other.rs", LIB_RS)]);
+    let dir = fixture.path().canonicalize().unwrap();
+
+    // Like `-i intraconv.toml` given in `dir`.
+    let config: RawFileConfig = toml::from_str(
+        r#"
+        ["src/lib.rs"]
+        "Vec" = ["struct.Vec.html"]
+        "#,
+    )
+    .unwrap();
+    let config = config.finish(&dir).unwrap();
+
+    let args = args(&[]);
+    let run_config = run_config(&args, &config, &dir);
+
+    // The file is given with a path that is not canonical, the canonical one
+    // is used to match the configuration.
+    let file: PathBuf = dir.join("src/../src/lib.rs");
+    let output = run_for_file(&file, &run_config, &crate_info())
+        .unwrap()
+        .output;
+    assert!(!output.contains("struct.Vec.html"), "{}", output);
+    assert!(output.contains("struct.Box.html"), "{}", output);
+
+    let output = run_for_file(&dir.join("src/other.rs"), &run_config, &crate_info())
+        .unwrap()
+        .output;
+    assert!(output.contains("struct.Vec.html"), "{}", output);
+}
+
+#[test]
+fn test_reports_order() {
+    let names: Vec<_> = (0..20).map(|i| format!("src/mod{:02}.rs", i)).collect();
+    let fixture = crate::fixture(
+        &names
+            .iter()
+            .map(|n| (n.as_str(), LIB_RS))
+            .collect::<Vec<_>>(),
+    );
+    let dir = fixture.path().canonicalize().unwrap();
+
+    let paths: Vec<_> = names.iter().map(|n| dir.join(n)).collect();
+    // Inserted in reverse, reported by path.
+    let files: BTreeMap<_, _> = paths
+        .iter()
+        .rev()
+        .map(|p| (p.clone(), (p.as_path(), None)))
+        .collect();
+
+    let args = args(&[]);
+    let reports = run_for_files(files, &run_config(&args, &NO_IGNORE, &dir), &HashMap::new());
+
+    assert_eq!(reports.len(), names.len());
+    for (report, name) in reports.iter().zip(&names) {
+        let report = report.as_ref().unwrap();
+        // Each report only contains the lines of its own file.
+        assert!(
+            report.starts_with(&format!("krate: {}\n", name)),
+            "{}",
+            report
+        );
+        assert_eq!(report.matches("krate: ").count(), 1, "{}", report);
+        assert_eq!(report.matches("struct.Vec.html").count(), 1, "{}", report);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_through_symlink() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = crate::fixture(&[("src/lib.rs", LIB_RS)]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let target = dir.join("src/lib.rs");
+    let link = dir.join("link.rs");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_atomically(&link, "new\n", &link).unwrap();
+
+    // The link is kept and the file it points to is replaced.
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new\n");
+    let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+#[test]
+fn test_only_changed_lines() {
+    let fixture = crate::fixture(&[("src/lib.rs", LIB_RS)]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let file = dir.join("src/lib.rs");
+
+    let mut changed_lines = git::ChangedLines::new();
+    changed_lines.insert(file.clone(), vec![2..=2]);
+    let args = args(&["--changed-since", "HEAD", "--only-changed-lines", "-a"]);
+    let config = RunConfig {
+        changed_lines: Some(&changed_lines),
+        ..run_config(&args, &NO_IGNORE, &dir)
+    };
+
+    let output = run_for_file(&file, &config, &crate_info()).unwrap().output;
+    assert!(!output.contains("struct.Vec.html"), "{}", output);
+    assert!(output.contains("struct.Box.html"), "{}", output);
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "//! [`Vec`](struct.Vec.html)\n//! [`Box`]\n"
+    );
+
+    // Files without changed lines are left unchanged.
+    changed_lines.clear();
+    let config = RunConfig {
+        changed_lines: Some(&changed_lines),
+        ..run_config(&args, &NO_IGNORE, &dir)
+    };
+    assert_eq!(
+        run_for_file(&file, &config, &crate_info()).unwrap().output,
+        ""
+    );
+}
+
+#[test]
+fn test_staged() {
+    let names: Vec<_> = (0..60).map(|i| format!("src/mod{:02}.rs", i)).collect();
+    let files: Vec<_> = names.iter().map(|n| (n.as_str(), "")).collect();
+    let fixture = crate::git_fixture(&files);
+    let dir = fixture.path().canonicalize().unwrap();
+    let paths: Vec<_> = names.iter().map(|n| dir.join(n)).collect();
+    let repo = git::Repo::open(&dir).unwrap();
+
+    for path in &paths {
+        std::fs::write(path, LIB_RS).unwrap();
+    }
+    crate::run_git(&dir, &["add", "--all"]);
+    // Files with unstaged changes are not written.
+    let unstaged = &paths[0];
+    std::fs::write(unstaged, format!("{}//! Unstaged.\n", LIB_RS)).unwrap();
+
+    let args = args(&["--staged", "-a"]);
+    let run_config = RunConfig {
+        repo: Some(&repo),
+        ..run_config(&args, &NO_IGNORE, &dir)
+    };
+    let files = paths
+        .iter()
+        .map(|p| (p.clone(), (p.as_path(), None)))
+        .collect();
+    let reports = run_for_files(files, &run_config, &HashMap::new());
+
+    // No failure to stage is reported after the files.
+    assert_eq!(reports.len(), paths.len());
+    let error = reports[0].as_ref().unwrap_err();
+    assert!(error.contains("the file has unstaged changes"), "{}", error);
+    assert!(repo.has_unstaged_changes(unstaged).unwrap());
+
+    // The staged content of the others is converted, written and staged
+    // again, all of them at once.
+    let converted = "//! [`Vec`]\n//! [`Box`]\n";
+    for (report, path) in reports.iter().zip(&paths).skip(1) {
+        assert!(report.as_ref().unwrap().contains("struct.Vec.html"));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), converted);
+        assert_eq!(repo.staged_content(path).unwrap(), converted.as_bytes());
+        assert!(!repo.has_unstaged_changes(path).unwrap());
+    }
+}
+
+#[test]
+fn test_convert_stdin_filename() {
+    let fixture = crate::fixture(&[
+        (
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[workspace]\n",
+        ),
+        ("src/lib.rs", ""),
+        ("src/a/b.rs", ""),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let filename = dir.join("src/a/b.rs");
+
+    let config: RawFileConfig = toml::from_str(
+        r#"
+        ["src/a/b.rs"]
+        "Vec" = ["struct.Vec.html"]
+        "#,
+    )
+    .unwrap();
+    let config = config.finish(&dir).unwrap();
+
+    let targets =
+        file_finder::crate_targets(Some(&dir.join("Cargo.toml")), &config, false).unwrap();
+    let target = file_finder::owning_target(&targets, &filename).unwrap();
+    let args = args(&["--stdin", "--stdin-filename", "src/a/b.rs"]);
+    let run_config = run_config(&args, &config, &dir);
+    let crate_info = target_crate_info(&run_config, &targets[target], &[]);
+    assert_eq!(crate_info.krate, "app");
+
+    let input = "\
+//! [`Vec`](struct.Vec.html)
+//! [`Foo`](../../app/struct.Foo.html)
+//! [`Bar`](../struct.Bar.html)
+//! [`Option`](../../../std/option/enum.Option.html)
+";
+    let actions = convert(
+        &filename,
+        &filename,
+        input.as_bytes(),
+        &run_config,
+        &crate_info,
+    )
+    .unwrap();
+    assert_eq!(
+        new_content(&actions),
+        "\
+//! [`Vec`](struct.Vec.html)
+//! [`Foo`](crate::Foo)
+//! [`Bar`](super::Bar)
+//! [`Option`](std::option::Option)
+"
+    );
+}
+
+#[test]
+fn test_out_dir() {
+    let fixture = crate::fixture(&[("src/lib.rs", LIB_RS), ("src/a/b.rs", "//! Nothing.\n")]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let out = dir.join("target/out");
+    std::fs::create_dir_all(&out).unwrap();
+
+    let paths = [dir.join("src/lib.rs"), dir.join("src/a/b.rs")];
+    let run = |extra: &[&str]| {
+        let files = paths
+            .iter()
+            .map(|p| (p.clone(), (p.as_path(), None)))
+            .collect();
+        let args = args(extra);
+        let run_config = RunConfig {
+            out_dir: Some((&out, &dir)),
+            ..run_config(&args, &NO_IGNORE, &dir)
+        };
+        for report in run_for_files(files, &run_config, &HashMap::new()) {
+            report.unwrap();
+        }
+    };
+    let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+
+    // Sources are never written, even with `-a`.
+    run(&["--out-dir", "target/out", "-a"]);
+    assert_eq!(read(&dir.join("src/lib.rs")), LIB_RS);
+    assert_eq!(read(&out.join("src/lib.rs")), "//! [`Vec`]\n//! [`Box`]\n");
+    // Unchanged files are only copied when asked.
+    assert!(!out.join("src/a/b.rs").exists());
+
+    run(&["--out-dir", "target/out", "--copy-unchanged"]);
+    assert_eq!(read(&out.join("src/a/b.rs")), "//! Nothing.\n");
+    assert_eq!(read(&dir.join("src/a/b.rs")), "//! Nothing.\n");
+}
+
+/// Workspace where `a` depends on `b`, with a binary and an example.
+fn workspace_fixture() -> tempfile::TempDir {
+    crate::fixture(&[
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        (
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n[dependencies]\nb = { path = \"../b\" }\n",
+        ),
+        ("a/src/lib.rs", ""),
+        ("a/src/bin/tool.rs", ""),
+        ("a/examples/demo.rs", ""),
+        (
+            "b/Cargo.toml",
+            "[package]\nname = \"b\"\nversion = \"0.1.0\"\n\n[lib]\npath = \"src/b.rs\"\n",
+        ),
+        ("b/src/b.rs", ""),
+    ])
+}
+
+#[test]
+fn test_convert_crate_roots() {
+    let fixture = workspace_fixture();
+    let dir = fixture.path().canonicalize().unwrap();
+    let manifest = dir.join("Cargo.toml");
+
+    let targets = file_finder::crate_targets(Some(&manifest), &NO_IGNORE, false).unwrap();
+    let deps = file_finder::workspace_deps(Some(&manifest));
+    let workspace_crates = workspace_crates(&targets);
+    let args = args(&[]);
+    let run_config = RunConfig {
+        deps: &deps,
+        ..run_config(&args, &NO_IGNORE, &dir)
+    };
+
+    let check = |file: &str, input: &str, expected: &str| {
+        let file = dir.join(file);
+        let target = &targets[file_finder::owning_target(&targets, &file).unwrap()];
+        let crate_info = target_crate_info(&run_config, target, &workspace_crates);
+        let actions = convert(&file, &file, input.as_bytes(), &run_config, &crate_info).unwrap();
+        assert_eq!(new_content(&actions), expected, "{}", file.display());
+    };
+
+    // Root files are at the root of their crate, whatever their name.
+    let to_b = "//! [X](../b/struct.X.html)\n";
+    check("a/src/lib.rs", to_b, "//! [X](b::X)\n");
+    check("a/src/bin/tool.rs", to_b, "//! [X](b::X)\n");
+    check("a/examples/demo.rs", to_b, "//! [X](b::X)\n");
+    check(
+        "b/src/b.rs",
+        "//! [Y](../b/struct.Y.html)\n",
+        "//! [Y](crate::Y)\n",
+    );
+}
+
+#[test]
+fn test_convert_relative_path() {
+    let fixture = crate::fixture(&[
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        (
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n[lib]\npath = \"lib/a.rs\"\n\n[dependencies]\nb = { path = \"../b\" }\n",
+        ),
+        ("a/lib/a.rs", ""),
+        ("a/lib/m.rs", ""),
+        ("b/Cargo.toml", "[package]\nname = \"b\"\nversion = \"0.1.0\"\n"),
+        ("b/src/lib.rs", ""),
+    ]);
+    let dir = fixture.path().canonicalize().unwrap();
+    let manifest = dir.join("Cargo.toml");
+
+    let targets = file_finder::crate_targets(Some(&manifest), &NO_IGNORE, false).unwrap();
+    let deps = file_finder::workspace_deps(Some(&manifest));
+    let workspace_crates = workspace_crates(&targets);
+    let args = args(&[]);
+    let run_config = RunConfig {
+        deps: &deps,
+        ..run_config(&args, &NO_IGNORE, &dir)
+    };
+
+    let canonical = dir.join("a/lib/m.rs");
+    let target = &targets[file_finder::owning_target(&targets, &canonical).unwrap()];
+    let crate_info = target_crate_info(&run_config, target, &workspace_crates);
+    let input = "//! [X](../../b/struct.X.html)\n";
+
+    // Like `echo a/lib/m.rs | cargo intraconv --files-from -` started in
+    // `dir`, or with the absolute path.
+    for path in &[Path::new("a/lib/m.rs"), &canonical] {
+        let actions =
+            convert(path, &canonical, input.as_bytes(), &run_config, &crate_info).unwrap();
+        assert_eq!(
+            new_content(&actions),
+            "//! [X](b::X)\n",
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_check_sections_crate_roots() {
+    let fixture = workspace_fixture();
+    let dir = fixture.path().canonicalize().unwrap();
+    let manifest = dir.join("Cargo.toml");
+    for krate in &["a", "tool", "demo"] {
+        let page = dir.join("target/doc").join(krate).join("index.html");
+        std::fs::create_dir_all(page.parent().unwrap()).unwrap();
+        std::fs::write(page, r#"<h1 id="usage">Usage</h1>"#).unwrap();
+    }
+
+    let targets = file_finder::crate_targets(Some(&manifest), &NO_IGNORE, false).unwrap();
+    let doc_root = dir.join("target/doc");
+    let args = args(&["--check-sections"]);
+    let run_config = RunConfig {
+        doc_root: Some(&doc_root),
+        ..run_config(&args, &NO_IGNORE, &dir)
+    };
+
+    let warnings = |file: &str| {
+        let file = dir.join(file);
+        let target = &targets[file_finder::owning_target(&targets, &file).unwrap()];
+        let crate_info = target_crate_info(&run_config, target, &[]);
+        let input = "//! [Usage](#usage)\n//! [Missing](#missing)\n";
+        let actions = convert(&file, &file, input.as_bytes(), &run_config, &crate_info).unwrap();
+        actions
+            .iter()
+            .flat_map(|a| match a {
+                Action::Replaced { warnings, .. } | Action::Flagged { warnings, .. } => {
+                    warnings.clone()
+                }
+                _ => Vec::new(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Fragments in root files are checked against the page of their crate.
+    for (file, krate) in &[
+        ("a/src/lib.rs", "a"),
+        ("a/src/bin/tool.rs", "tool"),
+        ("a/examples/demo.rs", "demo"),
+    ] {
+        let page = doc_root.join(krate).join("index.html");
+        assert_eq!(
+            warnings(file),
+            [format!(
+                "'#missing': Fragment '#missing' not found in '{}'",
+                page.display()
+            )],
+            "{}",
+            file
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{Action, TYPE_BLOCK_START};
+use regex::Regex;
 use std::io::{self, BufRead};
 
 /// Context for the check. It notably contains informations about the crate and
@@ -32,6 +33,27 @@ pub struct ConversionContext<'opts> {
     ///
     /// The tuple is (type block, end of type block, line of type block)
     type_blocks: Vec<(String, String, usize)>,
+
+    /// Item documented by each line of the file, see `find_doc_targets`.
+    ///
+    /// The line numbers start at one so the target of line `n` is at
+    /// index `n - 1`.
+    doc_targets: Vec<DocTarget>,
+}
+
+/// Kind of item a doc comment belongs to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DocTarget {
+    /// Not a doc comment or the item could not be found (e.g. in a markdown
+    /// file).
+    Unknown,
+    /// `//!` docs or docs on a `mod`: there is no `Self` type.
+    Module,
+    /// Docs on a type, a trait or an impl block for the given type.
+    Type(String),
+    /// Docs on any other item, like a function or a constant. There is a
+    /// `Self` type only when it is inside a type block.
+    Other,
 }
 
 impl<'opts> ConversionContext<'opts> {
@@ -44,6 +66,7 @@ impl<'opts> ConversionContext<'opts> {
             end_type_block: String::new(),
             type_block_line: usize::MAX,
             type_blocks: Vec::new(),
+            doc_targets: Vec::new(),
         }
     }
 
@@ -52,8 +75,31 @@ impl<'opts> ConversionContext<'opts> {
         self.curr_type_block.as_deref()
     }
 
+    /// Name of the type or trait `#method.name` links refer to on the current
+    /// line.
+    ///
+    /// Returns `None` when the current line documents a module or an item
+    /// that is outside of a type block, like a free function: there is no
+    /// such type then. When the documented item is unknown, the current type
+    /// block is used, or `Self` if there is none.
+    pub fn assoc_parent(&self) -> Option<&str> {
+        let target = self
+            .pos
+            .checked_sub(1)
+            .and_then(|idx| self.doc_targets.get(idx))
+            .unwrap_or(&DocTarget::Unknown);
+
+        match target {
+            DocTarget::Unknown => Some(self.current_type_block().unwrap_or("Self")),
+            DocTarget::Module => None,
+            DocTarget::Type(ty) => Some(ty),
+            DocTarget::Other if self.pos >= self.type_block_line => self.current_type_block(),
+            DocTarget::Other => None,
+        }
+    }
+
     /// Reference to the options for the context.
-    pub fn options(&self) -> &crate::ConversionOptions {
+    pub fn options(&self) -> &crate::ConversionOptions<'_> {
         &self.options
     }
 
@@ -72,6 +118,7 @@ impl<'opts> ConversionContext<'opts> {
         self.curr_type_block = None;
         self.end_type_block = String::new();
         self.type_blocks.clear();
+        self.doc_targets.clear();
 
         let mut lines = Vec::new();
         for l in reader.lines() {
@@ -79,6 +126,7 @@ impl<'opts> ConversionContext<'opts> {
         }
 
         self.type_blocks = find_type_blocks(lines.iter());
+        self.doc_targets = find_doc_targets(lines.iter());
 
         let mut actions = Vec::with_capacity(lines.len());
         for line in lines.into_iter() {
@@ -89,6 +137,21 @@ impl<'opts> ConversionContext<'opts> {
         Ok(actions)
     }
 
+    /// Action for a line that was not modified: it is only `Flagged` when
+    /// there are warnings about its links.
+    fn unchanged(&self, mut line: String, warnings: Vec<String>) -> Action {
+        line.push('\n');
+        if warnings.is_empty() {
+            Action::Unchanged { line }
+        } else {
+            Action::Flagged {
+                line,
+                pos: self.pos,
+                warnings,
+            }
+        }
+    }
+
     /// Transform a single line, returning the action.
     fn transform_line(&mut self, line: String) -> Action {
         // Updating the currently active `Self` type.
@@ -122,13 +185,10 @@ impl<'opts> ConversionContext<'opts> {
             }
         };
 
-        let transformed = match candidate.transform(self) {
+        let mut warnings = Vec::new();
+        let transformed = match candidate.transform(self, &mut warnings) {
             Some(t) => t,
-            None => {
-                let mut line = line;
-                line.push('\n');
-                return Action::Unchanged { line };
-            }
+            None => return self.unchanged(line, warnings),
         };
 
         if let Some(captures) = crate::LOCAL_PATH_LONG.captures(&transformed) {
@@ -150,9 +210,7 @@ impl<'opts> ConversionContext<'opts> {
         }
 
         if line == transformed {
-            let mut line = line;
-            line.push('\n');
-            Action::Unchanged { line }
+            self.unchanged(line, warnings)
         } else {
             let mut transformed = transformed;
             transformed.push('\n');
@@ -160,6 +218,7 @@ impl<'opts> ConversionContext<'opts> {
                 line,
                 new: transformed,
                 pos: self.pos,
+                warnings,
             }
         }
     }
@@ -207,5 +266,50 @@ where
     type_blocks
 }
 
+/// Returns the item documented by each line.
+///
+/// `//!` lines document the current module and `///` lines document the
+/// next line that is not a doc comment, an attribute, a comment or empty.
+/// All other lines have an `Unknown` target.
+fn find_doc_targets<S, I>(lines: I) -> Vec<DocTarget>
+where
+    S: AsRef<str>,
+    I: DoubleEndedIterator<Item = S>,
+{
+    lazy_static::lazy_static! {
+        static ref MOD: Regex = Regex::new(r"^\s*(?:pub(?:\(.+\))? )?mod \w+").unwrap();
+    }
+
+    let mut targets = Vec::new();
+    let mut next_item = DocTarget::Unknown;
+
+    for line in lines.rev() {
+        let line = line.as_ref();
+        let trimmed = line.trim_start();
+
+        let target = if trimmed.starts_with("//!") {
+            DocTarget::Module
+        } else if trimmed.starts_with("///") {
+            next_item.clone()
+        } else {
+            if trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with('#') {
+                // Keeping `next_item` as is.
+            } else if let Some(captures) = TYPE_BLOCK_START.captures(line) {
+                next_item = DocTarget::Type(captures.name("type").unwrap().as_str().into());
+            } else if MOD.is_match(line) {
+                next_item = DocTarget::Module;
+            } else {
+                next_item = DocTarget::Other;
+            }
+            DocTarget::Unknown
+        };
+
+        targets.push(target);
+    }
+
+    targets.reverse();
+    targets
+}
+
 #[cfg(test)]
 mod tests;
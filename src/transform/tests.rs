@@ -7,11 +7,8 @@ impl PartialEq<str> for Action {
         match self {
             Action::Unchanged { line } => line == other,
             Action::Deleted { line, pos: _ } => line == other,
-            Action::Replaced {
-                line: _,
-                new,
-                pos: _,
-            } => new == other,
+            Action::Replaced { new, .. } => new == other,
+            Action::Flagged { line, .. } => line == other,
         }
     }
 }
@@ -52,12 +49,20 @@ fn new() {
             favored_links: true,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         },
         pos: 0,
         curr_type_block: None,
         end_type_block: String::new(),
         type_block_line: usize::MAX,
         type_blocks: Vec::new(),
+        doc_targets: Vec::new(),
     };
 
     assert_eq!(
@@ -67,6 +72,13 @@ fn new() {
             favored_links: true,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -78,6 +90,13 @@ fn new() {
             favored_links: true,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -88,16 +107,30 @@ fn new() {
             favored_links: false,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
     assert_ne!(
         ConversionContext::with_options(ConversionOptions {
-            krate: krate,
+            krate,
             disambiguate: false,
             favored_links: false,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -109,6 +142,13 @@ fn new() {
             favored_links: true,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -119,6 +159,13 @@ fn new() {
             favored_links: false,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -129,6 +176,13 @@ fn new() {
             favored_links: true,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -139,6 +193,13 @@ fn new() {
             favored_links: false,
             ignored_links: &Default::default(),
             current_path: Path::new(""),
+            doc_dir: None,
+            html_root_url: None,
+            doc_roots: &[],
+            dependencies: None,
+            facade: crate::Facade::Std,
+            workspace_crates: &[],
+            module_depth: 0,
         }),
         ctx
     );
@@ -462,6 +523,26 @@ fn delete_local_links() {
         .is_deleted());
 }
 
+#[test]
+fn numbered_anchors_are_flagged() {
+    let mut ctx = CTX_KRATE_DIS_AND_FAV.clone();
+    ctx.set_current_type_block("Type".into());
+
+    match ctx.transform_line("/// [new]: #method.new-1".into()) {
+        Action::Replaced { new, warnings, .. } => {
+            assert_eq!(new, "/// [new]: Type::new()\n");
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].starts_with("'#method.new-1': "));
+        }
+        a => panic!("Unexpected action: {:?}", a),
+    }
+
+    match ctx.transform_line("/// [new]: #method.new".into()) {
+        Action::Replaced { warnings, .. } => assert!(warnings.is_empty()),
+        a => panic!("Unexpected action: {:?}", a),
+    }
+}
+
 #[test]
 fn section_add_nothing() {
     let mut ctx = CTX_KRATE_DIS_AND_FAV.clone();
@@ -571,3 +652,81 @@ fn type_block_some_to_none() {
     assert_eq!("", ctx.end_type_block);
     assert_eq!(usize::MAX, ctx.type_block_line);
 }
+
+#[test]
+fn doc_targets() {
+    let lines = [
+        "//! [a]: #method.a",
+        "",
+        "/// Doc.",
+        "#[derive(Debug)]",
+        "pub struct Type {",
+        "    /// Field doc.",
+        "    field: u8,",
+        "}",
+        "",
+        "/// Doc.",
+        "// Comment",
+        "pub fn free() {}",
+        "",
+        "/// Doc.",
+        "pub(crate) mod module;",
+        "[a]: #method.a",
+    ];
+
+    assert_eq!(
+        find_doc_targets(lines.iter()),
+        [
+            DocTarget::Module,
+            DocTarget::Unknown,
+            DocTarget::Type("Type".into()),
+            DocTarget::Unknown,
+            DocTarget::Unknown,
+            DocTarget::Other,
+            DocTarget::Unknown,
+            DocTarget::Unknown,
+            DocTarget::Unknown,
+            DocTarget::Other,
+            DocTarget::Unknown,
+            DocTarget::Unknown,
+            DocTarget::Unknown,
+            DocTarget::Module,
+            DocTarget::Unknown,
+            DocTarget::Unknown,
+        ]
+    );
+}
+
+#[test]
+fn assoc_items_follow_doc_targets() {
+    let file = "\
+//! [a]: #method.a
+/// [b]: #method.b
+pub trait Trait {
+    /// [c]: #method.c
+    fn c(&self);
+}
+
+/// [d]: #method.d
+pub fn free() {}
+
+impl Type {
+    /// [e]: #method.e
+    pub fn e(&self) {}
+}
+[f]: #method.f
+";
+
+    let mut ctx = CTX_KRATE_NO_DIS_BUT_FAV.clone();
+    let actions = ctx.transform_file(file.as_bytes()).unwrap();
+
+    assert!(matches!(&actions[0], Action::Flagged { .. }));
+    assert_eq!("//! [a]: #method.a\n", actions[0]);
+    assert_eq!("/// [b]: Trait::b()\n", actions[1]);
+    assert_eq!("    /// [c]: Trait::c()\n", actions[3]);
+    assert!(matches!(&actions[7], Action::Flagged { .. }));
+    assert_eq!("/// [d]: #method.d\n", actions[7]);
+    assert_eq!("    /// [e]: Type::e()\n", actions[11]);
+    // Unknown targets keep the `Self` fallback.
+    assert_eq!("[f]: Self::f()\n", actions[14]);
+}